@@ -0,0 +1,200 @@
+use crate::download::{fetch_blob_to, resolve_manifest, verify_signature, DownloadOptions};
+use crate::registry::{parse_image_ref, RegistryClient};
+use crate::tar;
+use crate::types::{Descriptor, ANNOTATION_TITLE, MEDIA_TYPE_OCI_EMPTY};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// ORAS 上传目录时使用的注解，值为 `true` 表示 layer 是需要解包的 tar.gz
+const ANNOTATION_UNPACK: &str = "io.deis.oras.content.unpack";
+/// 非空 config 的输出文件名
+const CONFIG_FILE_NAME: &str = "config.json";
+
+/// 下载 OCI 制品（Helm chart、WASM 模块、ORAS 文件等），
+/// 将每个 layer 按 `org.opencontainers.image.title` 注解保存为文件
+///
+/// 设置 cosign 公钥时先校验签名，通过后才下载内容。所有 Blob 都经过缓存、Blob 存储与 digest 校验；
+/// 文件名冲突（包括与 `config.json` 冲突）时报错，不互相覆盖。
+pub async fn extract_artifact(
+    image_ref: &str,
+    username: Option<String>,
+    password: Option<String>,
+    output_dir: &Path,
+    options: &DownloadOptions,
+) -> Result<Vec<String>> {
+    eprintln!("Parsing artifact reference: {}", image_ref);
+    let image = parse_image_ref(image_ref)?;
+
+    let scope = format!("repository:{}:pull", image.repository);
+    let mut client = RegistryClient::new(image.registry.clone(), username, password)?;
+
+    eprintln!("Authenticating...");
+    client.authenticate(&image.repository, &scope).await?;
+
+    let resolved = resolve_manifest(&client, &image.repository, &image.reference).await?;
+    let manifest = &resolved.manifest;
+
+    eprintln!(
+        "Artifact type: {}",
+        manifest.artifact_type().unwrap_or("unknown")
+    );
+
+    if let Some(public_key) = &options.cosign_public_key {
+        verify_signature(
+            &client,
+            &image.repository,
+            &resolved.digest,
+            &resolved.platform_digest,
+            public_key,
+            output_dir,
+        )
+        .await?;
+    }
+
+    // 临时目录与输出目录位于同一文件系统，下载完成后直接重命名
+    let staging_dir = output_dir.join(".blobs");
+    fs::create_dir_all(&staging_dir).await?;
+
+    let mut extracted_files = Vec::new();
+
+    // 空 config（`{}`）没有实际内容，无需保存
+    let save_config = manifest.config.media_type.as_deref() != Some(MEDIA_TYPE_OCI_EMPTY);
+    let file_names = output_names(&manifest.layers, save_config)?;
+
+    if save_config {
+        let blob_path = staging_dir.join(&manifest.config.digest);
+        fetch_blob_to(&client, &image.repository, &manifest.config, &blob_path, options).await?;
+        let config_path = output_dir.join(CONFIG_FILE_NAME);
+        fs::rename(&blob_path, &config_path).await?;
+        extracted_files.push(config_path.to_string_lossy().to_string());
+    }
+
+    for (idx, (layer, file_name)) in manifest.layers.iter().zip(file_names).enumerate() {
+        let Some(file_name) = file_name else {
+            eprintln!("Layer {}/{}: {} already written, skipping", idx + 1, manifest.layers.len(), layer.digest);
+            continue;
+        };
+        eprintln!(
+            "Layer {}/{}: {} -> {} ({})",
+            idx + 1,
+            manifest.layers.len(),
+            layer.digest,
+            file_name,
+            layer.media_type.as_deref().unwrap_or("unknown")
+        );
+
+        let blob_path = staging_dir.join(&layer.digest);
+        fetch_blob_to(&client, &image.repository, layer, &blob_path, options).await?;
+
+        let target_path = output_dir.join(&file_name);
+        if is_unpack_layer(layer) {
+            // ORAS 目录：解包到以 title 命名的目录
+            tar::extract_tar_archive(&blob_path, &target_path)?;
+            fs::remove_file(&blob_path).await?;
+        } else {
+            fs::rename(&blob_path, &target_path).await?;
+        }
+
+        extracted_files.push(target_path.to_string_lossy().to_string());
+    }
+
+    fs::remove_dir_all(&staging_dir).await?;
+
+    Ok(extracted_files)
+}
+
+/// 每个 layer 的输出文件名；同名且同 digest 的重复 layer 为 `None`（只写一次），
+/// 同名但内容不同或与 config 同名时报错
+fn output_names(layers: &[Descriptor], save_config: bool) -> Result<Vec<Option<String>>> {
+    let mut used: HashMap<String, &str> = HashMap::new();
+    let mut names = Vec::new();
+    for layer in layers {
+        let file_name = layer_file_name(layer);
+        if save_config && file_name == CONFIG_FILE_NAME {
+            return Err(anyhow!("Layer {} conflicts with the artifact config: {}", layer.digest, file_name));
+        }
+
+        match used.get(&file_name) {
+            Some(digest) if *digest == layer.digest => names.push(None),
+            Some(_) => return Err(anyhow!("Duplicate file name in artifact: {}", file_name)),
+            None => {
+                used.insert(file_name.clone(), &layer.digest);
+                names.push(Some(file_name));
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+/// 根据 title 注解确定 layer 的文件名
+///
+/// 只保留最后一级文件名，避免注解中的路径写到输出目录之外；
+/// 没有注解时使用 digest 命名。
+fn layer_file_name(layer: &Descriptor) -> String {
+    layer
+        .annotations
+        .as_ref()
+        .and_then(|a| a.get(ANNOTATION_TITLE))
+        .and_then(|title| PathBuf::from(title).file_name().map(|n| n.to_string_lossy().to_string()))
+        .filter(|name| name != "." && name != "..")
+        .unwrap_or_else(|| tar::sanitize_filename(&layer.digest))
+}
+
+/// layer 是否为 ORAS 打包的目录
+fn is_unpack_layer(layer: &Descriptor) -> bool {
+    layer
+        .annotations
+        .as_ref()
+        .and_then(|a| a.get(ANNOTATION_UNPACK))
+        .is_some_and(|v| v == "true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(digest: &str, title: Option<&str>) -> Descriptor {
+        Descriptor {
+            media_type: Some("application/octet-stream".to_string()),
+            size: 1,
+            digest: digest.to_string(),
+            platform: None,
+            artifact_type: None,
+            annotations: title.map(|t| HashMap::from([(ANNOTATION_TITLE.to_string(), t.to_string())])),
+        }
+    }
+
+    #[test]
+    fn output_names_use_title_or_digest() {
+        let layers = [
+            layer("sha256:a", Some("chart.tgz")),
+            layer("sha256:b", Some("../etc/values.yaml")),
+            layer("sha256:c", None),
+            layer("sha256:a", Some("chart.tgz")),
+        ];
+        let names = output_names(&layers, true).unwrap();
+        assert_eq!(
+            names,
+            [
+                Some("chart.tgz".to_string()),
+                Some("values.yaml".to_string()),
+                Some("sha256_c".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn output_names_reject_collisions() {
+        let layers = [layer("sha256:a", Some("app.wasm")), layer("sha256:b", Some("dir/app.wasm"))];
+        let error = output_names(&layers, false).unwrap_err();
+        assert_eq!(error.to_string(), "Duplicate file name in artifact: app.wasm");
+
+        let layers = [layer("sha256:a", Some("config.json"))];
+        assert!(output_names(&layers, true).is_err());
+        assert_eq!(output_names(&layers, false).unwrap(), [Some("config.json".to_string())]);
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// 本地 Blob 缓存，按 `blobs/sha256/<hex>` 布局存放，可在多次运行之间复用
+#[derive(Debug, Clone)]
+pub struct BlobCache {
+    root: PathBuf,
+}
+
+impl BlobCache {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Blob 在缓存中的路径
+    pub fn path(&self, digest: &str) -> PathBuf {
+        let (algorithm, hex) = digest.split_once(':').unwrap_or(("sha256", digest));
+        self.root.join("blobs").join(algorithm).join(hex)
+    }
+
+    /// 缓存中是否有完整的 Blob（按大小判断）
+    pub async fn contains(&self, digest: &str, size: u64) -> bool {
+        fs::metadata(self.path(digest))
+            .await
+            .is_ok_and(|m| m.len() == size)
+    }
+
+    /// 从缓存复制 Blob 到目标路径（优先硬链接），缓存未命中时返回 false
+    pub async fn restore(&self, digest: &str, size: u64, dest: &Path) -> Result<bool> {
+        if !self.contains(digest, size).await {
+            return Ok(false);
+        }
+
+        let cached = self.path(digest);
+        if dest.exists() {
+            fs::remove_file(dest).await?;
+        }
+        if fs::hard_link(&cached, dest).await.is_err() {
+            fs::copy(&cached, dest).await?;
+        }
+
+        Ok(true)
+    }
+
+    /// 将已下载并校验过的 Blob 存入缓存
+    pub async fn store(&self, digest: &str, src: &Path) -> Result<()> {
+        let cached = self.path(digest);
+        if cached.exists() {
+            return Ok(());
+        }
+
+        if let Some(parent) = cached.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        // 先写临时文件再重命名，避免中断后留下不完整的缓存
+        let temp = cached.with_extension("tmp");
+        if fs::hard_link(src, &temp).await.is_err() {
+            fs::copy(src, &temp).await?;
+        }
+        fs::rename(&temp, &cached).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use crate::destination::{self, Destination};
+use crate::digest;
+use crate::tar;
+use crate::types::{Descriptor, ManifestResponse, MEDIA_TYPE_OCI_MANIFEST};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::fs;
+
+/// OCI Index 媒体类型
+pub const MEDIA_TYPE_OCI_INDEX: &str = "application/vnd.oci.image.index.v1+json";
+/// Index 中记录镜像引用的注解
+pub const ANNOTATION_REF_NAME: &str = "org.opencontainers.image.ref.name";
+/// OCI 镜像布局标记文件
+const OCI_LAYOUT: &str = "oci-layout";
+/// OCI 镜像布局的根 Index，列出存储中的全部镜像
+const ROOT_INDEX: &str = "index.json";
+
+/// 每个镜像的 Index（OCI Index 格式），指向存储中的 Manifest
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageIndex {
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u32,
+    #[serde(rename = "mediaType")]
+    pub media_type: String,
+    pub manifests: Vec<Descriptor>,
+}
+
+/// 远程 Blob 存储（`REMOTE_CAS` 上传的 `blobs/sha256/<hex>` 布局），下载镜像时先于 registry 查找
+#[derive(Clone)]
+pub struct BlobStore {
+    destination: Arc<dyn Destination>,
+}
+
+impl std::fmt::Debug for BlobStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobStore")
+            .field("location", &self.destination.location())
+            .finish()
+    }
+}
+
+impl BlobStore {
+    /// `BLOB_STORE` 中的目标 URL（与 `DESTINATIONS` 格式相同）
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var("BLOB_STORE") {
+            Ok(url) if !url.is_empty() => Ok(Some(Self {
+                destination: destination::from_url(&url)?,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// 存储中是否有该 Blob（大小未知时视为存在），存储不可用时只输出警告并返回 false
+    pub async fn contains(&self, digest: &str, size: u64) -> bool {
+        match self.destination.stat(&blob_name(digest)).await {
+            Ok(Some(file)) => file.size.is_none() || file.size == Some(size),
+            Ok(None) => false,
+            Err(e) => {
+                eprintln!("⚠️  Blob store unavailable: {:#}", e);
+                false
+            }
+        }
+    }
+
+    /// 从存储下载 Blob 到 `path` 并校验 digest，存储中没有或内容不一致时返回 false
+    ///
+    /// 存储不可用时只输出警告，由调用方回退到 registry。
+    pub async fn fetch(&self, digest: &str, size: u64, path: &Path) -> Result<bool> {
+        if !self.contains(digest, size).await {
+            return Ok(false);
+        }
+
+        let name = blob_name(digest);
+        if let Err(e) = self.destination.download(&name, path).await {
+            eprintln!("⚠️  Failed to fetch {} from blob store: {:#}", digest, e);
+            let _ = fs::remove_file(path).await;
+            return Ok(false);
+        }
+
+        if let Err(e) = digest::verify_file(path, digest) {
+            eprintln!("⚠️  Blob store returned invalid data: {:#}", e);
+            fs::remove_file(path).await?;
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
+
+/// 上传统计
+#[derive(Debug, Default)]
+pub struct CasSummary {
+    pub uploaded: usize,
+    pub uploaded_bytes: u64,
+    pub skipped: usize,
+    pub skipped_bytes: u64,
+    /// Index 的远程路径
+    pub index: String,
+}
+
+/// Blob 在存储中的路径：`blobs/sha256/<hex>`
+pub fn blob_name(digest: &str) -> String {
+    let (algorithm, hex) = digest.split_once(':').unwrap_or(("sha256", digest));
+    format!("blobs/{}/{}", algorithm, hex)
+}
+
+/// 目标上是否已有大小一致的 Blob
+pub async fn has_blob(destination: &dyn Destination, digest: &str, size: u64) -> Result<bool> {
+    let existing = destination.stat(&blob_name(digest)).await?;
+    Ok(existing.and_then(|f| f.size) == Some(size))
+}
+
+/// 镜像 Index 在存储中的路径：`index/<name>.json`
+pub fn index_name(image_ref: &str) -> String {
+    format!("index/{}.json", tar::sanitize_filename(image_ref))
+}
+
+/// 将下载目录中的镜像逐个 Blob 上传到内容寻址存储
+///
+/// 目标上已存在且大小一致的 Blob 不再上传。Manifest 同样作为 Blob 保存，
+/// 最后上传的 Index 指向它，接收端看到 Index 时所有 Blob 都已完整。
+/// 根目录的 `index.json` 随之更新，存储整体是一个 OCI 镜像布局。
+pub async fn upload_image(
+    destination: &dyn Destination,
+    image_ref: &str,
+    image_dir: &Path,
+    manifest: &ManifestResponse,
+) -> Result<CasSummary> {
+    let mut summary = CasSummary::default();
+
+    let mut seen = HashSet::new();
+    let blobs: Vec<_> = std::iter::once(&manifest.config)
+        .chain(manifest.layers.iter())
+        .filter(|d| seen.insert(d.digest.clone()))
+        .collect();
+
+    for (idx, blob) in blobs.iter().enumerate() {
+        eprintln!("Blob {}/{}: {} ({} bytes)", idx + 1, blobs.len(), blob.digest, blob.size);
+        let local_path = image_dir.join("blobs").join(&blob.digest);
+        put_blob(destination, &local_path, &blob.digest, blob.size, &mut summary).await?;
+    }
+
+    // Manifest 按保存的内容寻址（转换 gzip 后与 registry 中的 digest 不同）
+    let manifest_path = image_dir.join("manifest.json");
+    let manifest_bytes = fs::read(&manifest_path).await?;
+    let manifest_digest = digest::sha256_digest(&manifest_bytes);
+    let manifest_size = manifest_bytes.len() as u64;
+    eprintln!("Manifest: {} ({} bytes)", manifest_digest, manifest_size);
+    put_blob(destination, &manifest_path, &manifest_digest, manifest_size, &mut summary).await?;
+
+    // 存储根目录同时是一个 OCI 镜像布局
+    if destination.stat(OCI_LAYOUT).await?.is_none() {
+        let layout_path = image_dir.join(OCI_LAYOUT);
+        fs::write(&layout_path, r#"{"imageLayoutVersion":"1.0.0"}"#).await?;
+        destination.put(&layout_path, OCI_LAYOUT).await?;
+    }
+
+    let descriptor = Descriptor {
+        media_type: Some(
+            manifest
+                .media_type
+                .clone()
+                .unwrap_or_else(|| MEDIA_TYPE_OCI_MANIFEST.to_string()),
+        ),
+        size: manifest_size,
+        digest: manifest_digest,
+        platform: None,
+        artifact_type: None,
+        annotations: Some(HashMap::from([(
+            ANNOTATION_REF_NAME.to_string(),
+            image_ref.to_string(),
+        )])),
+    };
+
+    let mut index = ImageIndex::new();
+    index.manifests.push(descriptor.clone());
+    let index_path = image_dir.join("index.json");
+    fs::write(&index_path, serde_json::to_vec_pretty(&index)?).await?;
+    summary.index = destination.put(&index_path, &index_name(image_ref)).await?;
+
+    update_root_index(destination, image_dir, descriptor).await?;
+
+    Ok(summary)
+}
+
+impl ImageIndex {
+    fn new() -> Self {
+        Self {
+            schema_version: 2,
+            media_type: MEDIA_TYPE_OCI_INDEX.to_string(),
+            manifests: Vec::new(),
+        }
+    }
+
+    /// 替换同一镜像引用的条目，没有时追加
+    fn upsert(&mut self, descriptor: Descriptor) {
+        let name = ref_name(&descriptor).map(str::to_string);
+        self.manifests
+            .retain(|d| name.is_none() || ref_name(d) != name.as_deref());
+        self.manifests.push(descriptor);
+    }
+}
+
+fn ref_name(descriptor: &Descriptor) -> Option<&str> {
+    descriptor
+        .annotations
+        .as_ref()?
+        .get(ANNOTATION_REF_NAME)
+        .map(String::as_str)
+}
+
+/// 在根 `index.json` 中记录镜像（读取、合并后整体覆盖，多个上传同时写入时以最后一次为准）
+async fn update_root_index(
+    destination: &dyn Destination,
+    image_dir: &Path,
+    descriptor: Descriptor,
+) -> Result<()> {
+    let mut index = match destination.stat(ROOT_INDEX).await? {
+        Some(_) => {
+            let data = destination.get(ROOT_INDEX).await?;
+            serde_json::from_slice(&data).map_err(|e| anyhow!("Invalid {}: {}", ROOT_INDEX, e))?
+        }
+        None => ImageIndex::new(),
+    };
+    index.upsert(descriptor);
+
+    let root_index_path = image_dir.join("root-index.json");
+    fs::write(&root_index_path, serde_json::to_vec_pretty(&index)?).await?;
+    destination.put(&root_index_path, ROOT_INDEX).await?;
+    Ok(())
+}
+
+/// 上传一个 Blob，目标上已存在且大小一致时跳过
+async fn put_blob(
+    destination: &dyn Destination,
+    local_path: &Path,
+    digest: &str,
+    size: u64,
+    summary: &mut CasSummary,
+) -> Result<()> {
+    if has_blob(destination, digest, size).await? {
+        eprintln!("Already present, skipping");
+        summary.skipped += 1;
+        summary.skipped_bytes += size;
+        return Ok(());
+    }
+
+    let local_size = fs::metadata(local_path)
+        .await
+        .map_err(|e| anyhow!("Failed to read {}: {}", local_path.display(), e))?
+        .len();
+    if local_size != size {
+        return Err(anyhow!(
+            "Size mismatch for {}: expected {}, got {}",
+            digest,
+            size,
+            local_size
+        ));
+    }
+
+    destination.put(local_path, &blob_name(digest)).await?;
+    summary.uploaded += 1;
+    summary.uploaded_bytes += size;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::destination::LocalDestination;
+
+    /// 在 `image_dir` 中准备只有 config 的最小镜像
+    fn write_image(image_dir: &Path, config: &[u8]) -> ManifestResponse {
+        let config_digest = digest::sha256_digest(config);
+        std::fs::create_dir_all(image_dir.join("blobs")).unwrap();
+        std::fs::write(image_dir.join("blobs").join(&config_digest), config).unwrap();
+
+        let manifest: ManifestResponse = serde_json::from_value(serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": MEDIA_TYPE_OCI_MANIFEST,
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "size": config.len(),
+                "digest": config_digest,
+            },
+            "layers": [],
+        }))
+        .unwrap();
+        std::fs::write(image_dir.join("manifest.json"), serde_json::to_vec(&manifest).unwrap()).unwrap();
+        manifest
+    }
+
+    #[tokio::test]
+    async fn root_index_lists_every_uploaded_image() {
+        let root = std::env::temp_dir().join(format!("cas-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let store = LocalDestination {
+            dir: root.join("store"),
+            retention: None,
+        };
+
+        let nginx_dir = root.join("nginx");
+        let nginx = write_image(&nginx_dir, b"{\"nginx\":1}");
+        upload_image(&store, "nginx:latest", &nginx_dir, &nginx).await.unwrap();
+
+        let redis_dir = root.join("redis");
+        let redis = write_image(&redis_dir, b"{\"redis\":1}");
+        upload_image(&store, "redis:7", &redis_dir, &redis).await.unwrap();
+
+        // 同一引用再次上传时替换原条目
+        let nginx = write_image(&nginx_dir, b"{\"nginx\":2}");
+        upload_image(&store, "nginx:latest", &nginx_dir, &nginx).await.unwrap();
+
+        let data = std::fs::read(root.join("store").join(ROOT_INDEX)).unwrap();
+        let index: ImageIndex = serde_json::from_slice(&data).unwrap();
+        assert_eq!(index.media_type, MEDIA_TYPE_OCI_INDEX);
+        let refs: Vec<_> = index.manifests.iter().filter_map(ref_name).collect();
+        assert_eq!(refs, ["redis:7", "nginx:latest"]);
+
+        // 每个条目都指向存储中的 Manifest Blob
+        let manifest_bytes = std::fs::read(nginx_dir.join("manifest.json")).unwrap();
+        let latest = &index.manifests[1];
+        assert_eq!(latest.digest, digest::sha256_digest(&manifest_bytes));
+        for descriptor in &index.manifests {
+            let blob = root.join("store").join(blob_name(&descriptor.digest));
+            assert_eq!(std::fs::metadata(blob).unwrap().len(), descriptor.size);
+        }
+        assert!(root.join("store").join(OCI_LAYOUT).exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// layer 压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Uncompressed,
+}
+
+impl Compression {
+    /// 根据 layer 媒体类型判断压缩格式，无法判断时返回 `None`
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        if media_type.ends_with("+zstd") {
+            Some(Self::Zstd)
+        } else if media_type.ends_with("+gzip") || media_type.ends_with(".gzip") {
+            Some(Self::Gzip)
+        } else if media_type.ends_with(".tar") {
+            Some(Self::Uncompressed)
+        } else {
+            None
+        }
+    }
+
+    /// 根据文件头的魔数判断压缩格式
+    pub fn from_magic(header: &[u8]) -> Self {
+        if header.starts_with(&ZSTD_MAGIC) {
+            Self::Zstd
+        } else if header.starts_with(&GZIP_MAGIC) {
+            Self::Gzip
+        } else {
+            Self::Uncompressed
+        }
+    }
+
+    /// 同时检查媒体类型与文件魔数，以实际内容为准
+    pub fn detect(path: &Path, media_type: Option<&str>) -> Result<Self> {
+        let mut header = [0u8; 4];
+        let mut file = File::open(path)?;
+        let n = file.read(&mut header)?;
+        let actual = Self::from_magic(&header[..n]);
+
+        if let Some(declared) = media_type.and_then(Self::from_media_type) {
+            if declared != actual {
+                eprintln!(
+                    "Warning: {} declares {:?} but content is {:?}",
+                    path.display(),
+                    declared,
+                    actual
+                );
+            }
+        }
+
+        Ok(actual)
+    }
+}
+
+/// 打开 layer 并返回解压后的 tar 流
+pub fn open_layer(path: &Path, media_type: Option<&str>) -> Result<Box<dyn Read>> {
+    let compression = Compression::detect(path, media_type)?;
+    let reader = BufReader::new(File::open(path)?);
+
+    Ok(match compression {
+        Compression::Gzip => Box::new(GzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+        Compression::Uncompressed => Box::new(reader),
+    })
+}
+
+/// 将 layer 流式转换为 gzip 压缩
+pub fn convert_to_gzip(input: &Path, media_type: Option<&str>, output: &Path) -> Result<()> {
+    let mut reader = open_layer(input, media_type)?;
+    let mut encoder = GzEncoder::new(File::create(output)?, flate2::Compression::default());
+    std::io::copy(&mut reader, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// 转换为 gzip 后对应的 layer 媒体类型
+pub fn gzip_media_type(media_type: Option<&str>) -> Result<String> {
+    let media_type = media_type.unwrap_or("application/vnd.oci.image.layer.v1.tar");
+
+    if let Some(base) = media_type.strip_suffix("+zstd") {
+        return Ok(format!("{}+gzip", base));
+    }
+
+    match media_type {
+        "application/vnd.oci.image.layer.v1.tar" => {
+            Ok("application/vnd.oci.image.layer.v1.tar+gzip".to_string())
+        }
+        "application/vnd.docker.image.rootfs.diff.tar" => {
+            Ok("application/vnd.docker.image.rootfs.diff.tar.gzip".to_string())
+        }
+        other if other.ends_with("+gzip") || other.ends_with(".gzip") => Ok(other.to_string()),
+        other => Err(anyhow!("Cannot convert layer media type to gzip: {}", other)),
+    }
+}
@@ -0,0 +1,169 @@
+use crate::download::select_manifest_for_platform;
+use crate::registry::{parse_image_ref, RegistryClient};
+use crate::types::{is_index_media_type, ManifestListResponse, ManifestResponse};
+use anyhow::Result;
+use reqwest::Body;
+
+/// 在两个 registry 之间直接复制镜像，不落地到本地磁盘
+///
+/// `all_platforms` 为 true 时保留完整的 Manifest List / OCI Index，
+/// 否则只复制 linux/amd64 镜像。
+pub async fn copy_image(
+    src_ref: &str,
+    dst_ref: &str,
+    src_credentials: (Option<String>, Option<String>),
+    dst_credentials: (Option<String>, Option<String>),
+    all_platforms: bool,
+) -> Result<String> {
+    let src_image = parse_image_ref(src_ref)?;
+    let dst_image = parse_image_ref(dst_ref)?;
+
+    eprintln!("Source: {}/{}:{}", src_image.registry, src_image.repository, src_image.reference);
+    eprintln!("Target: {}/{}:{}", dst_image.registry, dst_image.repository, dst_image.reference);
+
+    // 1. 分别认证源与目标 registry
+    let (src_username, src_password) = src_credentials;
+    let mut src = RegistryClient::new(src_image.registry.clone(), src_username, src_password)?;
+    let src_scope = format!("repository:{}:pull", src_image.repository);
+    eprintln!("Authenticating to source...");
+    src.authenticate(&src_image.repository, &src_scope).await?;
+
+    let (dst_username, dst_password) = dst_credentials;
+    let mut dst = RegistryClient::new(dst_image.registry.clone(), dst_username, dst_password)?;
+    let mut dst_scope = format!("repository:{}:pull,push", dst_image.repository);
+    if src_image.registry == dst_image.registry {
+        // 同一 registry 内跨仓库挂载需要源仓库的 pull 权限
+        dst_scope.push_str(&format!(" repository:{}:pull", src_image.repository));
+    }
+    eprintln!("Authenticating to target...");
+    dst.authenticate(&dst_image.repository, &dst_scope).await?;
+
+    let copier = Copier {
+        src: &src,
+        src_repository: &src_image.repository,
+        dst: &dst,
+        dst_repository: &dst_image.repository,
+    };
+
+    // 2. 获取源 Manifest
+    eprintln!("Fetching manifest...");
+    let (content_type, manifest_bytes) = src
+        .fetch_manifest_raw(&src_image.repository, &src_image.reference)
+        .await?;
+
+    if !is_index_media_type(&content_type) {
+        return copier
+            .copy_manifest(&content_type, manifest_bytes, &dst_image.reference)
+            .await;
+    }
+
+    if !all_platforms {
+        eprintln!("Manifest List detected, selecting linux/amd64...");
+        let manifest_list: serde_json::Value = serde_json::from_slice(&manifest_bytes)?;
+        let digest = select_manifest_for_platform(&manifest_list, "linux", "amd64", None).await?;
+        let (content_type, manifest_bytes) = src.fetch_manifest_raw(&src_image.repository, &digest).await?;
+        return copier
+            .copy_manifest(&content_type, manifest_bytes, &dst_image.reference)
+            .await;
+    }
+
+    // 3. 保留多架构：先按 digest 复制每个子 Manifest，再上传 Index 本身
+    let manifest_list: ManifestListResponse = serde_json::from_slice(&manifest_bytes)?;
+    eprintln!(
+        "Manifest List detected, copying {} platforms...",
+        manifest_list.manifests.len()
+    );
+
+    // 没有 platform 的条目（attestation、制品）同样被 Index 引用，必须一并复制
+    for (idx, entry) in manifest_list.manifests.iter().enumerate() {
+        let platform = entry
+            .platform
+            .as_ref()
+            .map_or_else(|| "no platform".to_string(), |p| format!("{}/{}", p.os, p.architecture));
+        eprintln!(
+            "Platform {}/{}: {} ({})",
+            idx + 1,
+            manifest_list.manifests.len(),
+            platform,
+            entry.digest
+        );
+        let (content_type, child_bytes) = src.fetch_manifest_raw(&src_image.repository, &entry.digest).await?;
+        copier
+            .copy_manifest(&content_type, child_bytes, &entry.digest)
+            .await?;
+    }
+
+    eprintln!("Pushing manifest list...");
+    let digest = dst
+        .put_manifest(&dst_image.repository, &dst_image.reference, &content_type, manifest_bytes)
+        .await?;
+    eprintln!("Manifest list pushed: {}", digest);
+
+    Ok(digest)
+}
+
+/// 源与目标仓库的组合
+struct Copier<'a> {
+    src: &'a RegistryClient,
+    src_repository: &'a str,
+    dst: &'a RegistryClient,
+    dst_repository: &'a str,
+}
+
+impl Copier<'_> {
+    /// 复制单个镜像 Manifest 及其引用的所有 Blob
+    async fn copy_manifest(
+        &self,
+        content_type: &str,
+        manifest_bytes: Vec<u8>,
+        dst_reference: &str,
+    ) -> Result<String> {
+        let manifest: ManifestResponse = serde_json::from_slice(&manifest_bytes)?;
+
+        let mut descriptors = vec![&manifest.config];
+        descriptors.extend(manifest.layers.iter());
+
+        for (idx, blob) in descriptors.iter().enumerate() {
+            eprintln!(
+                "Blob {}/{}: {} ({} bytes)",
+                idx + 1,
+                descriptors.len(),
+                blob.digest,
+                blob.size
+            );
+            self.copy_blob(&blob.digest, blob.size).await?;
+        }
+
+        let digest = self
+            .dst
+            .put_manifest(self.dst_repository, dst_reference, content_type, manifest_bytes)
+            .await?;
+        eprintln!("Manifest pushed: {}", digest);
+
+        Ok(digest)
+    }
+
+    /// 复制单个 Blob：目标已存在则跳过，同一 registry 内尝试挂载，否则流式转发
+    async fn copy_blob(&self, digest: &str, size: u64) -> Result<()> {
+        if self.dst.blob_exists(self.dst_repository, digest).await? {
+            eprintln!("Blob already exists, skipping");
+            return Ok(());
+        }
+
+        if self.src.registry() == self.dst.registry()
+            && self.dst.mount_blob(self.dst_repository, digest, self.src_repository).await?
+        {
+            eprintln!("Blob mounted from {}", self.src_repository);
+            return Ok(());
+        }
+
+        let resp = self.src.fetch_blob(self.src_repository, digest).await?;
+        let body = Body::wrap_stream(resp.bytes_stream());
+        self.dst
+            .upload_blob_stream(self.dst_repository, digest, size, body)
+            .await?;
+        eprintln!("Blob copied: {} ({} bytes)", digest, size);
+
+        Ok(())
+    }
+}
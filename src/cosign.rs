@@ -0,0 +1,118 @@
+use crate::digest;
+use crate::registry::RegistryClient;
+use crate::types::ManifestResponse;
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use std::path::Path;
+use tokio::fs;
+
+/// cosign simple-signing 载荷的媒体类型
+const SIMPLE_SIGNING_MEDIA_TYPE: &str = "application/vnd.dev.cosign.simplesigning.v1+json";
+/// 保存签名的 layer 注解
+const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+
+/// 根据 Manifest digest 生成 cosign 签名 tag（`sha256-<hex>.sig`）
+pub fn signature_tag(manifest_digest: &str) -> String {
+    format!("{}.sig", manifest_digest.replace(':', "-"))
+}
+
+/// 下载 cosign 签名并用本地公钥校验
+///
+/// 签名 Manifest 与 layers 保存到 `output_dir/signatures/`。只做离线校验，
+/// 不访问 Rekor 透明日志；没有任何签名通过校验时返回错误。
+pub async fn fetch_and_verify(
+    client: &RegistryClient,
+    repository: &str,
+    manifest_digest: &str,
+    public_key_pem: &str,
+    output_dir: &Path,
+) -> Result<()> {
+    let verifying_key = VerifyingKey::from_public_key_pem(public_key_pem)
+        .map_err(|e| anyhow!("Invalid cosign public key (expected ECDSA P-256 PEM): {}", e))?;
+
+    // 1. 获取签名 Manifest
+    let tag = signature_tag(manifest_digest);
+    eprintln!("Fetching cosign signature: {}", tag);
+    let (_, manifest_bytes) = client
+        .fetch_manifest_raw(repository, &tag)
+        .await
+        .map_err(|e| anyhow!("No cosign signature found for {}: {}", manifest_digest, e))?;
+    let manifest: ManifestResponse = serde_json::from_slice(&manifest_bytes)?;
+
+    let signatures_dir = output_dir.join("signatures");
+    let blobs_dir = signatures_dir.join("blobs");
+    fs::create_dir_all(&blobs_dir).await?;
+    fs::write(signatures_dir.join("manifest.json"), &manifest_bytes).await?;
+
+    // 2. 下载签名 config 与 layers
+    let mut descriptors = vec![&manifest.config];
+    descriptors.extend(manifest.layers.iter());
+    for blob in &descriptors {
+        let blob_path = blobs_dir.join(&blob.digest);
+        client.download_blob(repository, &blob.digest, &blob_path).await?;
+        digest::verify_file(&blob_path, &blob.digest)?;
+    }
+
+    // 3. 任意一个签名通过校验即可
+    for layer in &manifest.layers {
+        if layer.media_type.as_deref() != Some(SIMPLE_SIGNING_MEDIA_TYPE) {
+            continue;
+        }
+
+        let signature = match layer
+            .annotations
+            .as_ref()
+            .and_then(|a| a.get(SIGNATURE_ANNOTATION))
+        {
+            Some(signature) => signature,
+            None => continue,
+        };
+
+        let payload = fs::read(blobs_dir.join(&layer.digest)).await?;
+        match verify_payload(&verifying_key, &payload, signature, manifest_digest) {
+            Ok(()) => {
+                eprintln!("Cosign signature verified: {}", layer.digest);
+                return Ok(());
+            }
+            Err(e) => eprintln!("Signature {} rejected: {}", layer.digest, e),
+        }
+    }
+
+    Err(anyhow!(
+        "No valid cosign signature for {} matched the provided public key",
+        manifest_digest
+    ))
+}
+
+/// 校验 simple-signing 载荷的签名，并确认载荷指向期望的 Manifest
+fn verify_payload(
+    verifying_key: &VerifyingKey,
+    payload: &[u8],
+    signature_b64: &str,
+    manifest_digest: &str,
+) -> Result<()> {
+    let signature_der = base64::engine::general_purpose::STANDARD.decode(signature_b64)?;
+    let signature = Signature::from_der(&signature_der)?;
+    verifying_key
+        .verify(payload, &signature)
+        .map_err(|_| anyhow!("signature does not match payload"))?;
+
+    let payload: serde_json::Value = serde_json::from_slice(payload)?;
+    let signed_digest = payload
+        .pointer("/critical/image/docker-manifest-digest")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("payload is missing docker-manifest-digest"))?;
+
+    if signed_digest != manifest_digest {
+        return Err(anyhow!(
+            "payload signs {}, expected {}",
+            signed_digest,
+            manifest_digest
+        ));
+    }
+
+    Ok(())
+}
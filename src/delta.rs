@@ -0,0 +1,178 @@
+use crate::digest;
+use crate::download::resolve_manifest;
+use crate::registry::{parse_image_ref, RegistryClient};
+use crate::tar;
+use crate::types::{Descriptor, ManifestResponse};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// 增量包中的说明文件名
+pub const DELTA_MANIFEST: &str = "delta.json";
+
+/// 增量包说明：哪些 Blob 随包传输，哪些需要从基础镜像取得
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeltaManifest {
+    /// 目标镜像引用
+    pub image: String,
+    /// 基础镜像引用或 Manifest 文件路径
+    pub base: String,
+    /// 增量包中包含的 Blob
+    pub blobs: Vec<Descriptor>,
+    /// 需要从基础镜像归档中复制的 Blob
+    pub base_blobs: Vec<Descriptor>,
+}
+
+/// 获取基础镜像的 Blob digest 集合
+///
+/// `base` 为已存在的文件时按上次运行保存的 `manifest.json` 读取，否则作为镜像引用从 registry 获取。
+pub async fn base_blobs(
+    base: &str,
+    username: Option<String>,
+    password: Option<String>,
+) -> Result<HashSet<String>> {
+    let manifest: ManifestResponse = if Path::new(base).is_file() {
+        eprintln!("Reading base manifest: {}", base);
+        serde_json::from_slice(&fs::read(base).await?)?
+    } else {
+        eprintln!("Resolving base image: {}", base);
+        let image = parse_image_ref(base)?;
+        let scope = format!("repository:{}:pull", image.repository);
+        let mut client = RegistryClient::new(image.registry.clone(), username, password)?;
+        client.authenticate(&image.repository, &scope).await?;
+        resolve_manifest(&client, &image.repository, &image.reference).await?.manifest
+    };
+
+    let mut digests: HashSet<String> = manifest.layers.into_iter().map(|l| l.digest).collect();
+    digests.insert(manifest.config.digest);
+
+    Ok(digests)
+}
+
+/// 在下载目录中写入增量说明，返回基础镜像提供的字节数
+pub async fn write_delta_manifest(
+    image_dir: &Path,
+    image_ref: &str,
+    base: &str,
+    base_digests: &HashSet<String>,
+) -> Result<u64> {
+    let manifest: ManifestResponse =
+        serde_json::from_slice(&fs::read(image_dir.join("manifest.json")).await?)?;
+
+    let mut seen = HashSet::new();
+    let mut delta = DeltaManifest {
+        image: image_ref.to_string(),
+        base: base.to_string(),
+        blobs: Vec::new(),
+        base_blobs: Vec::new(),
+    };
+    for descriptor in std::iter::once(&manifest.config).chain(manifest.layers.iter()) {
+        if !seen.insert(descriptor.digest.clone()) {
+            continue;
+        }
+        if base_digests.contains(&descriptor.digest) {
+            delta.base_blobs.push(descriptor.clone());
+        } else {
+            delta.blobs.push(descriptor.clone());
+        }
+    }
+
+    let reused_bytes = delta.base_blobs.iter().map(|b| b.size).sum();
+    eprintln!(
+        "Delta: {} blobs included, {} blobs from base",
+        delta.blobs.len(),
+        delta.base_blobs.len()
+    );
+
+    fs::write(image_dir.join(DELTA_MANIFEST), serde_json::to_string_pretty(&delta)?).await?;
+
+    Ok(reused_bytes)
+}
+
+/// 由基础镜像归档与增量包重新组装完整的镜像归档
+///
+/// `base` 与 `delta` 可以是 tar.gz 归档或已解包的目录；输出与下载模式的归档格式相同。
+pub async fn apply_delta(base: &Path, delta: &Path, output_file: &Path) -> Result<()> {
+    let staging_dir = output_file.with_extension("apply");
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir).await?;
+    }
+    fs::create_dir_all(&staging_dir).await?;
+
+    let result = assemble(base, delta, &staging_dir, output_file).await;
+    fs::remove_dir_all(&staging_dir).await?;
+
+    result
+}
+
+async fn assemble(base: &Path, delta: &Path, staging_dir: &Path, output_file: &Path) -> Result<()> {
+    let image_dir = staging_dir.join("image");
+    let delta_dir = unpack(delta, &image_dir)?;
+    if delta_dir != image_dir {
+        copy_dir(&delta_dir, &image_dir)?;
+    }
+
+    let delta_path = image_dir.join(DELTA_MANIFEST);
+    let delta: DeltaManifest = serde_json::from_slice(
+        &fs::read(&delta_path)
+            .await
+            .map_err(|e| anyhow!("Not a delta package ({} missing): {}", DELTA_MANIFEST, e))?,
+    )?;
+    eprintln!("Image: {}", delta.image);
+    eprintln!("Base: {}", delta.base);
+
+    let base_dir = unpack(base, &staging_dir.join("base"))?;
+
+    // 从基础镜像复制缺少的 Blob
+    let blobs_dir = image_dir.join("blobs");
+    for blob in &delta.base_blobs {
+        let source = base_dir.join("blobs").join(&blob.digest);
+        if !source.exists() {
+            return Err(anyhow!("Blob not found in base: {}", blob.digest));
+        }
+        eprintln!("Reusing blob from base: {}", blob.digest);
+        digest::verify_file(&source, &blob.digest)?;
+        fs::copy(&source, blobs_dir.join(&blob.digest)).await?;
+    }
+
+    for blob in &delta.blobs {
+        digest::verify_file(&blobs_dir.join(&blob.digest), &blob.digest)?;
+    }
+
+    fs::remove_file(&delta_path).await?;
+
+    if output_file.exists() {
+        fs::remove_file(output_file).await?;
+    }
+    tar::create_tar_archive(&image_dir, output_file, None)?;
+
+    Ok(())
+}
+
+/// 目录直接使用，归档解包到 `target` 后返回
+fn unpack(source: &Path, target: &Path) -> Result<PathBuf> {
+    if source.is_dir() {
+        return Ok(source.to_path_buf());
+    }
+
+    tar::extract_tar_archive(source, target)?;
+    Ok(target.to_path_buf())
+}
+
+/// 递归复制目录
+fn copy_dir(source: &Path, target: &Path) -> Result<()> {
+    std::fs::create_dir_all(target)?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let path = entry.path();
+        let dest = target.join(entry.file_name());
+        if path.is_dir() {
+            copy_dir(&path, &dest)?;
+        } else {
+            std::fs::copy(&path, &dest)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,363 @@
+use crate::ftp::FtpConfig;
+use crate::http::HttpConfig;
+use crate::retention::RetentionPolicy;
+use crate::s3::S3Config;
+use crate::sftp::SftpConfig;
+use crate::webdav::WebDavConfig;
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, Stream, StreamExt};
+use reqwest::Url;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::oneshot;
+
+/// 数据块流：数据源出错时以错误结束
+pub type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// 远程文件信息
+#[derive(Debug, Clone)]
+pub struct RemoteFile {
+    /// 相对于目标根目录的文件名
+    pub name: String,
+    /// 文件大小，列目录时可能未知
+    pub size: Option<u64>,
+    /// 修改时间，服务器不提供时为空
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// 上传目标：本地目录、FTP、SFTP、S3、HTTP PUT 或 WebDAV
+///
+/// 文件名相对于目标的根目录，可以包含子目录（`blobs/sha256/<hex>`），上传时自动创建。
+pub trait Destination: Send + Sync {
+    /// 用于日志的位置（不含密码）
+    fn location(&self) -> String;
+
+    /// `GITHUB_OUTPUT` 中记录远程路径的键
+    fn output_key(&self) -> &'static str;
+
+    /// 上传后清理旧归档的策略
+    fn retention(&self) -> Option<&RetentionPolicy> {
+        None
+    }
+
+    /// 上传本地文件，返回远程路径
+    ///
+    /// 本地目录、FTP、SFTP 与 WebDAV 先写入同目录的临时文件（`.<name>…part`），校验后再重命名；
+    /// S3 对象在上传完成时才可见；HTTP PUT 直接写入最终地址，中断时可能留下不完整的文件。
+    fn put<'a>(&'a self, local_path: &'a Path, name: &'a str) -> BoxFuture<'a, Result<String>>;
+
+    /// 流式上传：数据源结束后按 `summary`（总大小与 SHA-256）确认，失败时不留下不完整的文件
+    fn put_stream<'a>(
+        &'a self,
+        name: &'a str,
+        data: ByteStream,
+        summary: oneshot::Receiver<(u64, String)>,
+    ) -> BoxFuture<'a, Result<String>>;
+
+    /// 下载文件内容（只用于元数据等小文件）
+    fn get<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<u8>>>;
+
+    /// 下载文件到本地路径，返回字节数
+    fn download<'a>(&'a self, name: &'a str, local_path: &'a Path) -> BoxFuture<'a, Result<u64>>;
+
+    /// 文件信息，不存在时返回 None
+    fn stat<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Option<RemoteFile>>>;
+
+    /// 根目录中的文件（不含子目录）
+    fn list(&self) -> BoxFuture<'_, Result<Vec<RemoteFile>>>;
+
+    fn delete<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// 本次运行的全部目标：`FTP_SERVER` 等环境变量配置的目标，以及 `DESTINATIONS` 中按 URL 指定的目标
+pub fn from_env() -> Result<Vec<Arc<dyn Destination>>> {
+    let mut destinations: Vec<Arc<dyn Destination>> = Vec::new();
+    if let Some(config) = FtpConfig::from_env()? {
+        destinations.push(Arc::new(config));
+    }
+    if let Some(config) = SftpConfig::from_env()? {
+        destinations.push(Arc::new(config));
+    }
+    if let Some(config) = HttpConfig::from_env() {
+        destinations.push(Arc::new(config));
+    }
+    if let Some(config) = S3Config::from_env()? {
+        destinations.push(Arc::new(config));
+    }
+
+    // 以逗号、空白或换行分隔
+    let urls = std::env::var("DESTINATIONS").unwrap_or_default();
+    for url in urls.split(|c: char| c == ',' || c.is_whitespace()).filter(|u| !u.is_empty()) {
+        destinations.push(from_url(url)?);
+    }
+
+    Ok(destinations)
+}
+
+/// 按 URL scheme 创建目标
+///
+/// `file://`、`ftp://`、`ftps://`、`sftp://`、`s3://`、`http(s)://`（HTTP PUT）、`webdav(s)://`。
+pub fn from_url(url: &str) -> Result<Arc<dyn Destination>> {
+    let parsed = Url::parse(url).map_err(|e| anyhow!("Invalid destination {}: {}", redact(url), e))?;
+
+    let destination: Arc<dyn Destination> = match parsed.scheme() {
+        "file" => Arc::new(LocalDestination::from_url(&parsed)?),
+        "ftp" | "ftps" => Arc::new(FtpConfig::from_url(&parsed)?),
+        "sftp" => Arc::new(SftpConfig::from_url(&parsed)?),
+        "s3" => Arc::new(S3Config::from_url(&parsed)?),
+        "http" | "https" => Arc::new(HttpConfig::from_url(&parsed)?),
+        "webdav" | "webdavs" => Arc::new(WebDavConfig::from_url(&parsed)?),
+        scheme => return Err(anyhow!("Unsupported destination scheme: {}", scheme)),
+    };
+
+    Ok(destination)
+}
+
+/// 本地文件的文件名
+pub fn file_name(path: &Path) -> Result<String> {
+    Ok(path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid file path: {}", path.display()))?
+        .to_string_lossy()
+        .to_string())
+}
+
+/// URL 查询参数（已解码）
+pub fn query(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// URL 中的用户名与密码（已解码）
+pub fn credentials(url: &Url) -> (Option<String>, Option<String>) {
+    let decode = |s: &str| percent_encoding::percent_decode_str(s).decode_utf8_lossy().to_string();
+    let username = Some(url.username()).filter(|u| !u.is_empty()).map(decode);
+    (username, url.password().map(decode))
+}
+
+/// URL 路径（已解码）
+pub fn path(url: &Url) -> String {
+    percent_encoding::percent_decode_str(url.path()).decode_utf8_lossy().to_string()
+}
+
+/// 查询参数中的重试次数（默认 3）
+pub fn retries(url: &Url) -> Result<u32> {
+    match query(url, "retries") {
+        Some(retries) => retries.parse().map_err(|_| anyhow!("Invalid retries: {}", retries)),
+        None => Ok(3),
+    }
+}
+
+/// 查询参数 `keep` 与 `days` 指定的保留策略
+pub fn retention(url: &Url) -> Result<Option<RetentionPolicy>> {
+    RetentionPolicy::from_values(query(url, "keep"), query(url, "days"), ["keep", "days"])
+}
+
+/// 将响应体写入本地文件，返回字节数
+pub async fn write_body(response: reqwest::Response, local_path: &Path) -> Result<u64> {
+    let mut file = tokio::fs::File::create(local_path).await?;
+    let mut body = response.bytes_stream();
+    let mut written = 0u64;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+    }
+    file.flush().await?;
+    Ok(written)
+}
+
+/// 去掉 URL 中的密码，用于日志
+pub fn redact(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("****"));
+            parsed.to_string()
+        }
+        _ => url.to_string(),
+    }
+}
+
+/// 在 `dir` 下拼接相对文件名
+pub fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir.trim_end_matches('/'), name)
+    }
+}
+
+/// 本地目录目标：先写入 `.<name>.part`，完成后重命名
+#[derive(Debug, Clone)]
+pub struct LocalDestination {
+    pub dir: PathBuf,
+    pub retention: Option<RetentionPolicy>,
+}
+
+impl LocalDestination {
+    /// `file:///path/to/dir?keep=5`
+    pub fn from_url(url: &Url) -> Result<Self> {
+        let dir = url
+            .to_file_path()
+            .map_err(|_| anyhow!("Invalid file destination: {}", url))?;
+        Ok(Self {
+            dir,
+            retention: retention(url)?,
+        })
+    }
+
+    /// 目标路径与同目录下的临时路径，并创建父目录
+    async fn prepare(&self, name: &str) -> Result<(PathBuf, PathBuf)> {
+        let target = self.dir.join(name);
+        let parent = target.parent().unwrap_or(&self.dir).to_path_buf();
+        tokio::fs::create_dir_all(&parent)
+            .await
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+        let temp = parent.join(format!(".{}.part", file_name(&target)?));
+        Ok((target, temp))
+    }
+
+    async fn put_file(&self, local_path: &Path, name: &str) -> Result<String> {
+        let (target, temp) = self.prepare(name).await?;
+
+        let result = async {
+            let size = tokio::fs::copy(local_path, &temp).await?;
+            let expected = tokio::fs::metadata(local_path).await?.len();
+            if size != expected {
+                return Err(anyhow!("Size mismatch for {}: expected {}, got {}", name, expected, size));
+            }
+            tokio::fs::rename(&temp, &target).await?;
+            Ok(())
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&temp).await;
+        }
+        result?;
+
+        Ok(target.display().to_string())
+    }
+
+    async fn put_data(
+        &self,
+        name: &str,
+        mut data: ByteStream,
+        summary: oneshot::Receiver<(u64, String)>,
+    ) -> Result<String> {
+        let (target, temp) = self.prepare(name).await?;
+
+        let result = async {
+            let mut file = tokio::fs::File::create(&temp).await?;
+            let mut written = 0u64;
+            while let Some(chunk) = data.next().await {
+                let chunk = chunk?;
+                file.write_all(&chunk).await?;
+                written += chunk.len() as u64;
+            }
+            file.flush().await?;
+            drop(file);
+
+            let (size, _) = summary.await.map_err(|_| anyhow!("Archive stream aborted"))?;
+            if size != written {
+                return Err(anyhow!("Size mismatch for {}: expected {}, got {}", name, size, written));
+            }
+            tokio::fs::rename(&temp, &target).await?;
+            Ok(())
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&temp).await;
+        }
+        result?;
+
+        Ok(target.display().to_string())
+    }
+
+    async fn stat_file(&self, name: &str) -> Result<Option<RemoteFile>> {
+        match tokio::fs::metadata(self.dir.join(name)).await {
+            Ok(metadata) => Ok(Some(RemoteFile {
+                name: name.to_string(),
+                size: Some(metadata.len()),
+                modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list_files(&self) -> Result<Vec<RemoteFile>> {
+        let mut files = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_file() {
+                files.push(RemoteFile {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    size: Some(metadata.len()),
+                    modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+                });
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+impl Destination for LocalDestination {
+    fn location(&self) -> String {
+        format!("file://{}", self.dir.display())
+    }
+
+    fn output_key(&self) -> &'static str {
+        "local_path"
+    }
+
+    fn retention(&self) -> Option<&RetentionPolicy> {
+        self.retention.as_ref()
+    }
+
+    fn put<'a>(&'a self, local_path: &'a Path, name: &'a str) -> BoxFuture<'a, Result<String>> {
+        self.put_file(local_path, name).boxed()
+    }
+
+    fn put_stream<'a>(
+        &'a self,
+        name: &'a str,
+        data: ByteStream,
+        summary: oneshot::Receiver<(u64, String)>,
+    ) -> BoxFuture<'a, Result<String>> {
+        self.put_data(name, data, summary).boxed()
+    }
+
+    fn get<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<u8>>> {
+        async move { Ok(tokio::fs::read(self.dir.join(name)).await?) }.boxed()
+    }
+
+    fn download<'a>(&'a self, name: &'a str, local_path: &'a Path) -> BoxFuture<'a, Result<u64>> {
+        async move { Ok(tokio::fs::copy(self.dir.join(name), local_path).await?) }.boxed()
+    }
+
+    fn stat<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Option<RemoteFile>>> {
+        self.stat_file(name).boxed()
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<RemoteFile>>> {
+        self.list_files().boxed()
+    }
+
+    fn delete<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move { Ok(tokio::fs::remove_file(self.dir.join(name)).await?) }.boxed()
+    }
+}
@@ -1,16 +1,53 @@
+use crate::cache::BlobCache;
+use crate::cas::BlobStore;
+use crate::compression::{self, Compression};
+use crate::cosign;
+use crate::digest;
+use crate::referrers;
 use crate::registry::{parse_image_ref, RegistryClient};
-use crate::types::ManifestResponse;
+use crate::types::{is_index_media_type, Descriptor, ManifestResponse};
 use anyhow::{anyhow, Result};
+use std::collections::HashSet;
 use std::path::Path;
 use tokio::fs;
 
+/// 下载选项
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    /// cosign 公钥（PEM），设置后镜像必须带有可通过校验的签名
+    pub cosign_public_key: Option<String>,
+    /// 需要一并下载的 referrer 制品 artifactType（`*` 表示全部），为空时不下载
+    pub referrer_artifact_types: Vec<String>,
+    /// 将 zstd / 未压缩的 layer 转换为 gzip（目标环境不支持 zstd 时）
+    pub gzip_layers: bool,
+    /// 本地 Blob 缓存，命中时不再从 registry 下载
+    pub cache: Option<BlobCache>,
+    /// 远程 Blob 存储，本地缓存未命中时先于 registry 查找
+    pub blob_store: Option<BlobStore>,
+    /// 增量模式下基础镜像已有的 Blob，不下载也不打包
+    pub skip_blobs: HashSet<String>,
+}
+
+/// 下载结果
+pub struct DownloadedImage {
+    /// 写入输出目录的文件
+    pub files: Vec<String>,
+    /// 引用解析出的顶层 Manifest digest
+    pub digest: String,
+    /// 选中平台的 Manifest digest
+    pub platform_digest: String,
+    /// 保存到 `manifest.json` 的 Manifest（转换 gzip 后的描述符）
+    pub manifest: ManifestResponse,
+}
+
 /// 下载完整的 Docker 镜像
 pub async fn download_image(
     image_ref: &str,
     username: Option<String>,
     password: Option<String>,
     output_dir: &Path,
-) -> Result<Vec<String>> {
+    options: &DownloadOptions,
+) -> Result<DownloadedImage> {
     // 1. 解析镜像引用
     eprintln!("Parsing image reference: {}", image_ref);
     let image = parse_image_ref(image_ref)?;
@@ -26,42 +63,46 @@ pub async fn download_image(
     eprintln!("Authenticating...");
     client.authenticate(&image.repository, &scope).await?;
 
-    // 3. 获取 Manifest
-    eprintln!("Fetching manifest...");
-    let (content_type, manifest_json) =
-        client.fetch_manifest(&image.repository, &image.reference).await?;
-
-    // 4. 判断是否为 Manifest List（多架构）
-    let manifest = if content_type.contains("manifest.list") || content_type.contains("index.v1") {
-        eprintln!("Manifest List detected, selecting linux/amd64...");
-        let digest = select_manifest_for_platform(&manifest_json, "linux", "amd64", None).await?;
-
-        // 使用选中的 digest 重新请求完整的 manifest
-        eprintln!("Fetching specific manifest for linux/amd64...");
-        let (_, specific_manifest) = client.fetch_manifest(&image.repository, &digest).await?;
-        specific_manifest
-    } else {
-        manifest_json
-    };
+    // 3. 获取 Manifest（多架构时选择 linux/amd64）
+    let resolved = resolve_manifest(&client, &image.repository, &image.reference).await?;
+    let mut manifest = resolved.manifest.clone();
+    if manifest.is_artifact() {
+        eprintln!(
+            "OCI artifact detected: {}",
+            manifest.artifact_type().unwrap_or("unknown")
+        );
+    }
 
-    // 5. 解析 Manifest
-    let manifest: ManifestResponse = serde_json::from_value(manifest.clone())?;
+    // 4. 校验 cosign 签名（先于下载 layers，失败时不再打包）
+    if let Some(public_key) = &options.cosign_public_key {
+        verify_signature(
+            &client,
+            &image.repository,
+            &resolved.digest,
+            &resolved.platform_digest,
+            public_key,
+            output_dir,
+        )
+        .await?;
+    }
 
-    // 6. 创建输出目录
+    // 5. 创建输出目录
     let blobs_dir = output_dir.join("blobs");
     fs::create_dir_all(&blobs_dir).await?;
 
     let mut downloaded_files = Vec::new();
 
-    // 7. 下载 Config
-    eprintln!("Downloading config: {}", manifest.config.digest);
-    let config_path = blobs_dir.join(&manifest.config.digest);
-    client
-        .download_blob(&image.repository, &manifest.config.digest, &config_path)
-        .await?;
-    downloaded_files.push(config_path.to_string_lossy().to_string());
+    // 6. 下载 Config
+    if options.skip_blobs.contains(&manifest.config.digest) {
+        eprintln!("Config present in base, skipping: {}", manifest.config.digest);
+    } else {
+        eprintln!("Downloading config: {}", manifest.config.digest);
+        let config_path = blobs_dir.join(&manifest.config.digest);
+        fetch_blob_to(&client, &image.repository, &manifest.config, &config_path, options).await?;
+        downloaded_files.push(config_path.to_string_lossy().to_string());
+    }
 
-    // 8. 下载所有 Layers
+    // 7. 下载所有 Layers
     eprintln!("Downloading {} layers...", manifest.layers.len());
     for (idx, layer) in manifest.layers.iter().enumerate() {
         eprintln!(
@@ -72,27 +113,243 @@ pub async fn download_image(
             layer.size
         );
 
+        if options.skip_blobs.contains(&layer.digest) {
+            eprintln!("Layer present in base, skipping");
+            continue;
+        }
+
         let layer_path = blobs_dir.join(&layer.digest);
-        client
-            .download_blob(&image.repository, &layer.digest, &layer_path)
-            .await?;
+        fetch_blob_to(&client, &image.repository, layer, &layer_path, options).await?;
 
         downloaded_files.push(layer_path.to_string_lossy().to_string());
     }
 
     eprintln!("All layers downloaded successfully!");
 
-    // 9. 保存 manifest
+    // 8. 保存 manifest：原样保存 registry 返回的字节，只有 layer 被改写时才重新序列化
+    let mut manifest_content = resolved.manifest_bytes.clone();
+    if options.gzip_layers && convert_layers_to_gzip(&mut manifest, &blobs_dir, &mut downloaded_files)? {
+        manifest_content = serde_json::to_vec_pretty(&manifest)?;
+    }
+
     let manifest_path = output_dir.join("manifest.json");
-    let manifest_content = serde_json::to_string_pretty(&manifest)?;
     fs::write(&manifest_path, manifest_content).await?;
     downloaded_files.push(manifest_path.to_string_lossy().to_string());
 
-    Ok(downloaded_files)
+    // 9. 下载 SBOM / attestation 等 referrer 制品
+    if !options.referrer_artifact_types.is_empty() {
+        let mut referrers = referrers::discover(&client, &image.repository, &resolved.platform_digest).await?;
+        if let Some(index) = &resolved.index {
+            referrers.extend(referrers::buildkit_attestations(index, &resolved.platform_digest)?);
+            referrers.extend(referrers::discover(&client, &image.repository, &resolved.digest).await?);
+        }
+
+        let mut seen = HashSet::new();
+        referrers.retain(|r| seen.insert(r.digest.clone()));
+        referrers.retain(|r| referrers::matches(r, &options.referrer_artifact_types));
+
+        eprintln!("Found {} matching referrers", referrers.len());
+        if !referrers.is_empty() {
+            let files = referrers::download_referrers(&client, &image.repository, &referrers, output_dir).await?;
+            downloaded_files.extend(files);
+        }
+    }
+
+    Ok(DownloadedImage {
+        files: downloaded_files,
+        digest: resolved.digest,
+        platform_digest: resolved.platform_digest,
+        manifest,
+    })
+}
+
+/// 解析后的镜像 Manifest
+pub struct ResolvedManifest {
+    /// 引用解析出的顶层 Manifest digest（多架构时为 Index 的 digest）
+    pub digest: String,
+    /// 多架构镜像的 Manifest List / OCI Index
+    pub index: Option<serde_json::Value>,
+    /// 选中平台的 Manifest digest（单架构时与 `digest` 相同）
+    pub platform_digest: String,
+    pub manifest: ManifestResponse,
+    /// 选中平台 Manifest 的原始字节（digest 为 `platform_digest`）
+    pub manifest_bytes: Vec<u8>,
+}
+
+/// 获取 Manifest，多架构时选择 linux/amd64
+///
+/// Index 中没有 linux/amd64、只有一个不带 platform 的条目时（OCI 制品的 Index）选择该条目。
+pub async fn resolve_manifest(
+    client: &RegistryClient,
+    repository: &str,
+    reference: &str,
+) -> Result<ResolvedManifest> {
+    eprintln!("Fetching manifest...");
+    let (content_type, manifest_bytes) = client.fetch_manifest_raw(repository, reference).await?;
+    let manifest_digest = digest::sha256_digest(&manifest_bytes);
+    eprintln!("Manifest digest: {}", manifest_digest);
+
+    // 判断是否为 Manifest List（多架构）
+    if !is_index_media_type(&content_type) {
+        let manifest: ManifestResponse = serde_json::from_slice(&manifest_bytes)?;
+        return Ok(ResolvedManifest {
+            digest: manifest_digest.clone(),
+            index: None,
+            platform_digest: manifest_digest,
+            manifest,
+            manifest_bytes,
+        });
+    }
+
+    eprintln!("Manifest List detected, selecting linux/amd64...");
+    let index: serde_json::Value = serde_json::from_slice(&manifest_bytes)?;
+    let platform_digest = match select_manifest_for_platform(&index, "linux", "amd64", None).await {
+        Ok(digest) => digest,
+        Err(e) => platformless_manifest(&index).ok_or(e)?,
+    };
+
+    // 使用选中的 digest 重新请求完整的 manifest
+    eprintln!("Fetching specific manifest: {}", platform_digest);
+    let (_, specific_bytes) = client.fetch_manifest_raw(repository, &platform_digest).await?;
+    let specific_digest = digest::sha256_digest(&specific_bytes);
+    if specific_digest != platform_digest {
+        return Err(anyhow!(
+            "Manifest digest mismatch: expected {}, got {}",
+            platform_digest,
+            specific_digest
+        ));
+    }
+    let manifest: ManifestResponse = serde_json::from_slice(&specific_bytes)?;
+
+    Ok(ResolvedManifest {
+        digest: manifest_digest,
+        index: Some(index),
+        platform_digest,
+        manifest,
+        manifest_bytes: specific_bytes,
+    })
+}
+
+/// Index 中唯一不带 platform 的 Manifest（不含 BuildKit attestation）
+fn platformless_manifest(index: &serde_json::Value) -> Option<String> {
+    let manifests = index.get("manifests")?.as_array()?;
+    let mut candidates = manifests.iter().filter(|m| {
+        m.get("platform").is_none()
+            && m.get("annotations")
+                .and_then(|a| a.get(referrers::REFERENCE_TYPE_ANNOTATION))
+                .is_none()
+    });
+
+    let digest = candidates.next()?.get("digest")?.as_str()?.to_string();
+    if candidates.next().is_some() {
+        return None;
+    }
+    eprintln!("Selected platform-less manifest digest: {}", digest);
+    Some(digest)
+}
+
+/// 下载 Blob；依次查找本地缓存、远程 Blob 存储与 registry，下载完成后校验 digest（无论是否启用缓存）并存入缓存
+pub(crate) async fn fetch_blob_to(
+    client: &RegistryClient,
+    repository: &str,
+    descriptor: &Descriptor,
+    path: &Path,
+    options: &DownloadOptions,
+) -> Result<()> {
+    let cache = options.cache.as_ref();
+    if let Some(cache) = cache {
+        if cache.restore(&descriptor.digest, descriptor.size, path).await? {
+            eprintln!("Blob restored from cache: {}", descriptor.digest);
+            return Ok(());
+        }
+    }
+
+    let from_store = match &options.blob_store {
+        Some(store) => store.fetch(&descriptor.digest, descriptor.size, path).await?,
+        None => false,
+    };
+    if from_store {
+        eprintln!("Blob fetched from blob store: {}", descriptor.digest);
+    } else {
+        client.download_blob(repository, &descriptor.digest, path).await?;
+    }
+
+    digest::verify_file(path, &descriptor.digest)?;
+    if let Some(cache) = cache {
+        cache.store(&descriptor.digest, path).await?;
+    }
+
+    Ok(())
+}
+
+/// 将非 gzip 的 layer 转换为 gzip，并更新 Manifest 中的描述符；返回是否有 layer 被转换
+fn convert_layers_to_gzip(
+    manifest: &mut ManifestResponse,
+    blobs_dir: &Path,
+    downloaded_files: &mut [String],
+) -> Result<bool> {
+    // 同一个 layer 可能在 Manifest 中出现多次
+    let mut converted: std::collections::HashMap<String, Descriptor> = std::collections::HashMap::new();
+
+    for layer in manifest.layers.iter_mut() {
+        if let Some(gzip_layer) = converted.get(&layer.digest) {
+            *layer = gzip_layer.clone();
+            continue;
+        }
+
+        let blob_path = blobs_dir.join(&layer.digest);
+        if Compression::detect(&blob_path, layer.media_type.as_deref())? == Compression::Gzip {
+            continue;
+        }
+
+        eprintln!("Converting layer to gzip: {}", layer.digest);
+        let media_type = compression::gzip_media_type(layer.media_type.as_deref())?;
+        let temp_path = blobs_dir.join(format!("{}.gzip", layer.digest));
+        compression::convert_to_gzip(&blob_path, layer.media_type.as_deref(), &temp_path)?;
+
+        let gzip_digest = digest::sha256_file(&temp_path)?;
+        let gzip_path = blobs_dir.join(&gzip_digest);
+        std::fs::rename(&temp_path, &gzip_path)?;
+        std::fs::remove_file(&blob_path)?;
+
+        let old_path = blob_path.to_string_lossy().to_string();
+        for file in downloaded_files.iter_mut().filter(|f| **f == old_path) {
+            *file = gzip_path.to_string_lossy().to_string();
+        }
+
+        let original_digest = std::mem::replace(&mut layer.digest, gzip_digest);
+        layer.size = std::fs::metadata(&gzip_path)?.len();
+        layer.media_type = Some(media_type);
+        converted.insert(original_digest, layer.clone());
+    }
+
+    Ok(!converted.is_empty())
+}
+
+/// 校验 cosign 签名：优先使用 tag 解析出的 digest，多架构时再尝试平台 Manifest 的 digest
+pub async fn verify_signature(
+    client: &RegistryClient,
+    repository: &str,
+    manifest_digest: &str,
+    platform_digest: &str,
+    public_key: &str,
+    output_dir: &Path,
+) -> Result<()> {
+    let result = cosign::fetch_and_verify(client, repository, manifest_digest, public_key, output_dir).await;
+
+    match result {
+        Err(e) if platform_digest != manifest_digest => {
+            eprintln!("{}", e);
+            cosign::fetch_and_verify(client, repository, platform_digest, public_key, output_dir)
+                .await
+                .map_err(|e2| anyhow!("Signature verification failed: {}; {}", e, e2))
+        }
+        other => other,
+    }
 }
 
 /// 从 Manifest List 中选择指定平台的 Manifest Digest
-async fn select_manifest_for_platform(
+pub async fn select_manifest_for_platform(
     manifest_list: &serde_json::Value,
     os: &str,
     arch: &str,
@@ -105,22 +362,32 @@ async fn select_manifest_for_platform(
 
     // 查找匹配的 manifest
     for manifest in manifests {
+        // 跳过 BuildKit attestation manifest（platform 为 unknown/unknown）
+        if manifest
+            .get("annotations")
+            .and_then(|a| a.get(referrers::REFERENCE_TYPE_ANNOTATION))
+            .is_some()
+        {
+            continue;
+        }
+
         if let Some(platform) = manifest.get("platform") {
             let platform_os = platform.get("os").and_then(|v| v.as_str());
             let platform_arch = platform.get("architecture").and_then(|v| v.as_str());
             let platform_variant = platform.get("variant").and_then(|v| v.as_str());
 
-            if platform_os == Some(os) && platform_arch == Some(arch) {
-                if variant.is_none() || platform_variant == variant {
-                    // 获取 digest
-                    let digest = manifest
-                        .get("digest")
-                        .and_then(|v| v.as_str())
-                        .ok_or_else(|| anyhow!("Missing digest"))?;
-
-                    eprintln!("Selected manifest digest: {}", digest);
-                    return Ok(digest.to_string());
-                }
+            if platform_os == Some(os)
+                && platform_arch == Some(arch)
+                && (variant.is_none() || platform_variant == variant)
+            {
+                // 获取 digest
+                let digest = manifest
+                    .get("digest")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow!("Missing digest"))?;
+
+                eprintln!("Selected manifest digest: {}", digest);
+                return Ok(digest.to_string());
             }
         }
     }
@@ -132,3 +399,26 @@ async fn select_manifest_for_platform(
         variant
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn platformless_manifest_requires_a_single_entry() {
+        let attestation = json!({
+            "digest": "sha256:att",
+            "platform": { "os": "unknown", "architecture": "unknown" },
+            "annotations": { referrers::REFERENCE_TYPE_ANNOTATION: "attestation-manifest" },
+        });
+        let index = json!({ "manifests": [{ "digest": "sha256:artifact" }, attestation] });
+        assert_eq!(platformless_manifest(&index).as_deref(), Some("sha256:artifact"));
+
+        let index = json!({ "manifests": [{ "digest": "sha256:a" }, { "digest": "sha256:b" }] });
+        assert_eq!(platformless_manifest(&index), None);
+
+        let index = json!({ "manifests": [{ "digest": "sha256:arm", "platform": { "os": "linux", "architecture": "arm64" } }] });
+        assert_eq!(platformless_manifest(&index), None);
+    }
+}
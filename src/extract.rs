@@ -0,0 +1,185 @@
+use crate::compression;
+use crate::download::resolve_manifest;
+use crate::registry::{parse_image_ref, RegistryClient};
+use crate::rootfs::{normalize_path, Flattener};
+use anyhow::{anyhow, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::io::Read;
+use std::path::Path;
+use tar::{Archive, Builder};
+use tokio::fs;
+
+/// 从镜像中提取匹配路径的文件到输出目录
+///
+/// layers 自顶向下逐个下载并处理，遵循 whiteout 规则。所有路径都已确定
+/// （找到文件、被删除或位于 opaque 目录中）后，不再下载更下层的 layers。
+pub async fn extract_paths(
+    image_ref: &str,
+    username: Option<String>,
+    password: Option<String>,
+    patterns: &[String],
+    output_dir: &Path,
+) -> Result<Vec<String>> {
+    let patterns: Vec<String> = patterns.iter().map(|p| normalize_path(p)).collect();
+    let matcher = build_matcher(&patterns)?;
+
+    eprintln!("Parsing image reference: {}", image_ref);
+    let image = parse_image_ref(image_ref)?;
+
+    let scope = format!("repository:{}:pull", image.repository);
+    let mut client = RegistryClient::new(image.registry.clone(), username, password)?;
+
+    eprintln!("Authenticating...");
+    client.authenticate(&image.repository, &scope).await?;
+
+    let resolved = resolve_manifest(&client, &image.repository, &image.reference).await?;
+    let layers = &resolved.manifest.layers;
+
+    let staging_dir = output_dir.join(".extract");
+    fs::create_dir_all(&staging_dir).await?;
+
+    // 匹配的条目先写入临时 tar，最后统一解包（由 tar 处理链接与目录）
+    let selected_tar = staging_dir.join("selected.tar");
+    let builder = Builder::new(std::fs::File::create(&selected_tar)?);
+    let mut flattener = Flattener::new(builder).with_filter(move |path| matches(&matcher, path));
+
+    for (idx, layer) in layers.iter().enumerate().rev() {
+        eprintln!(
+            "Layer {}/{}: {} ({} bytes)",
+            idx + 1,
+            layers.len(),
+            layer.digest,
+            layer.size
+        );
+
+        let layer_path = staging_dir.join(&layer.digest);
+        client
+            .download_blob(&image.repository, &layer.digest, &layer_path)
+            .await?;
+        flattener.add_layer(|| compression::open_layer(&layer_path, layer.media_type.as_deref()))?;
+        fs::remove_file(&layer_path).await?;
+
+        if !flattener.has_pending_links()
+            && patterns.iter().all(|p| flattener.is_resolved(literal_prefix(p)))
+        {
+            if idx > 0 {
+                eprintln!("All paths resolved, skipping {} lower layers", idx);
+            }
+            break;
+        }
+    }
+
+    flattener.finish()?;
+
+    let extracted_files = unpack_selected(std::fs::File::open(&selected_tar)?, output_dir)?;
+
+    fs::remove_dir_all(&staging_dir).await?;
+
+    if extracted_files.is_empty() {
+        return Err(anyhow!("No files matched: {}", patterns.join(", ")));
+    }
+
+    Ok(extracted_files)
+}
+
+/// 将合并后选中的条目解包到输出目录，返回写入的路径
+fn unpack_selected<R: Read>(selected: R, output_dir: &Path) -> Result<Vec<String>> {
+    let mut archive = Archive::new(selected);
+    let mut extracted_files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().to_string();
+        if entry.unpack_in(output_dir)? {
+            extracted_files.push(output_dir.join(&path).to_string_lossy().to_string());
+        }
+    }
+
+    Ok(extracted_files)
+}
+
+/// 编译路径 glob；`*` 不跨越 `/`，`**` 匹配任意层级
+fn build_matcher(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+    }
+    Ok(builder.build()?)
+}
+
+/// 路径本身或其任意上级目录匹配时视为选中（匹配目录即提取整个目录）
+fn matches(matcher: &GlobSet, path: &str) -> bool {
+    if matcher.is_match(path) {
+        return true;
+    }
+
+    let mut current = path;
+    while let Some(idx) = current.rfind('/') {
+        current = &current[..idx];
+        if matcher.is_match(current) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// glob 中第一个通配符之前的目录部分，决定该模式何时不再受下层影响
+fn literal_prefix(pattern: &str) -> &str {
+    match pattern.find(['*', '?', '[', '{']) {
+        Some(idx) => pattern[..idx].rsplit_once('/').map_or("", |(prefix, _)| prefix),
+        None => pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rootfs::tests::{flatten, layer, Item};
+
+    #[test]
+    fn extracts_selected_path_across_layers() {
+        let lower = layer(&[
+            ("etc", Item::Dir),
+            ("etc/app", Item::Dir),
+            ("etc/app/a.conf", Item::File("a")),
+            ("etc/app/b.conf", Item::File("b")),
+            ("etc/other.conf", Item::File("other")),
+        ]);
+        let upper = layer(&[
+            ("etc/app", Item::Dir),
+            ("etc/app/.wh.a.conf", Item::File("")),
+            ("etc/app/c.conf", Item::File("c")),
+        ]);
+
+        let patterns = vec![normalize_path("/etc/app")];
+        let matcher = build_matcher(&patterns).unwrap();
+        let flattener = Flattener::new(Builder::new(Vec::new())).with_filter(move |path| matches(&matcher, path));
+        let selected = flatten(flattener, &[lower, upper]);
+
+        let output_dir = std::env::temp_dir().join(format!("extract-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&output_dir);
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let mut extracted = unpack_selected(selected.as_slice(), &output_dir).unwrap();
+        extracted.sort();
+
+        let app_dir = output_dir.join("etc/app");
+        let expected: Vec<String> = [app_dir.clone(), app_dir.join("b.conf"), app_dir.join("c.conf")]
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(extracted, expected);
+        assert_eq!(std::fs::read_to_string(app_dir.join("b.conf")).unwrap(), "b");
+        assert_eq!(std::fs::read_to_string(app_dir.join("c.conf")).unwrap(), "c");
+        assert!(!app_dir.join("a.conf").exists());
+        assert!(!output_dir.join("etc/other.conf").exists());
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn literal_prefix_stops_at_first_wildcard() {
+        assert_eq!(literal_prefix("etc/app/*.conf"), "etc/app");
+        assert_eq!(literal_prefix("etc/passwd"), "etc/passwd");
+        assert_eq!(literal_prefix("**/*.so"), "");
+    }
+}
@@ -0,0 +1,220 @@
+use crate::destination::{self, ByteStream, Destination, RemoteFile};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, Stream};
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, LAST_MODIFIED};
+use reqwest::{Body, Client, Method, RequestBuilder, StatusCode, Url};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio_util::io::ReaderStream;
+
+/// HTTP PUT 上传配置
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// 上传目录地址，文件名总是追加在其后
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl HttpConfig {
+    /// 从环境变量读取配置，未设置 `HTTP_UPLOAD_URL` 时返回 None
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("HTTP_UPLOAD_URL").ok().filter(|u| !u.is_empty())?;
+
+        Some(Self {
+            url,
+            username: std::env::var("HTTP_UPLOAD_USERNAME").ok().filter(|u| !u.is_empty()),
+            password: std::env::var("HTTP_UPLOAD_PASSWORD").ok(),
+        })
+    }
+
+    /// `http(s)://[user:pass@]host/path/`，认证信息从 URL 中取出
+    pub fn from_url(url: &Url) -> Result<Self> {
+        let (username, password) = destination::credentials(url);
+        let mut url = url.clone();
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+
+        Ok(Self {
+            url: url.to_string(),
+            username,
+            password,
+        })
+    }
+
+    /// 文件的上传地址
+    ///
+    /// 地址总是视为目录：分卷、校验和与内容寻址的 Blob 都需要各自的地址，不能共用一个文件地址。
+    pub fn target_url(&self, file_name: &str) -> String {
+        destination::join(&self.url, file_name)
+    }
+}
+
+impl Destination for HttpConfig {
+    fn location(&self) -> String {
+        self.url.clone()
+    }
+
+    fn output_key(&self) -> &'static str {
+        "http_url"
+    }
+
+    fn put<'a>(&'a self, local_path: &'a Path, name: &'a str) -> BoxFuture<'a, Result<String>> {
+        upload_file(self, local_path, name).boxed()
+    }
+
+    fn put_stream<'a>(
+        &'a self,
+        name: &'a str,
+        data: ByteStream,
+        summary: oneshot::Receiver<(u64, String)>,
+    ) -> BoxFuture<'a, Result<String>> {
+        async move {
+            let url = upload_stream(self, name, data).await?;
+            // 数据源失败时请求体会以错误结束，这里再确认一次
+            summary.await.map_err(|_| anyhow!("Archive stream aborted"))?;
+            Ok(url)
+        }
+        .boxed()
+    }
+
+    fn get<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<u8>>> {
+        async move {
+            let response = request(self, Method::GET, name)?.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(anyhow!("HTTP GET failed: {}", status));
+            }
+            Ok(response.bytes().await?.to_vec())
+        }
+        .boxed()
+    }
+
+    fn download<'a>(&'a self, name: &'a str, local_path: &'a Path) -> BoxFuture<'a, Result<u64>> {
+        async move {
+            let response = request(self, Method::GET, name)?.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(anyhow!("HTTP GET failed: {}", status));
+            }
+            destination::write_body(response, local_path).await
+        }
+        .boxed()
+    }
+
+    fn stat<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Option<RemoteFile>>> {
+        async move {
+            let response = request(self, Method::HEAD, name)?.send().await?;
+            match response.status() {
+                StatusCode::NOT_FOUND => Ok(None),
+                status if status.is_success() => Ok(Some(remote_file(name, response.headers()))),
+                status => Err(anyhow!("HTTP HEAD failed: {}", status)),
+            }
+        }
+        .boxed()
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<RemoteFile>>> {
+        async { Err(anyhow!("HTTP PUT destinations cannot list files")) }.boxed()
+    }
+
+    fn delete<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            let response = request(self, Method::DELETE, name)?.send().await?;
+            let status = response.status();
+            if !status.is_success() && status != StatusCode::NOT_FOUND {
+                return Err(anyhow!("HTTP DELETE failed: {}", status));
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// 由响应头中的大小与修改时间构造文件信息
+pub fn remote_file(name: &str, headers: &HeaderMap) -> RemoteFile {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    RemoteFile {
+        name: name.to_string(),
+        size: header(CONTENT_LENGTH).and_then(|v| v.parse().ok()),
+        modified: header(LAST_MODIFIED)
+            .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+            .map(|t| t.with_timezone(&Utc)),
+    }
+}
+
+/// 带认证的请求
+fn request(config: &HttpConfig, method: Method, name: &str) -> Result<RequestBuilder> {
+    let client = Client::builder().timeout(Duration::from_secs(300)).build()?;
+    let mut request = client.request(method, config.target_url(name));
+    if let Some(username) = &config.username {
+        request = request.basic_auth(username, config.password.as_deref());
+    }
+    Ok(request)
+}
+
+/// 以 PUT 上传本地文件到 `name`，返回目标地址
+pub async fn upload_file(config: &HttpConfig, local_path: &Path, name: &str) -> Result<String> {
+    let size = tokio::fs::metadata(local_path).await?.len();
+    let file = tokio::fs::File::open(local_path).await?;
+
+    put(config, name, Body::wrap_stream(ReaderStream::new(file)), Some(size)).await
+}
+
+/// 以 PUT 上传数据流（分块传输），数据源出错时请求中断
+pub async fn upload_stream<S>(config: &HttpConfig, file_name: &str, data: S) -> Result<String>
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Send + 'static,
+{
+    put(config, file_name, Body::wrap_stream(data), None).await
+}
+
+async fn put(config: &HttpConfig, file_name: &str, body: Body, size: Option<u64>) -> Result<String> {
+    let url = config.target_url(file_name);
+    eprintln!("Uploading to {}...", url);
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(6 * 3600))
+        .build()?;
+    let mut request = client.put(&url).body(body);
+    if let Some(size) = size {
+        request = request.header(CONTENT_LENGTH, size);
+    }
+    if let Some(username) = &config.username {
+        request = request.basic_auth(username, config.password.as_deref());
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!("HTTP upload failed: {} - {}", status, text));
+    }
+
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_url_always_appends_name() {
+        for url in ["https://files.example.com/images", "https://files.example.com/images/"] {
+            let config = HttpConfig {
+                url: url.to_string(),
+                username: None,
+                password: None,
+            };
+            assert_eq!(config.target_url("nginx.tar.gz"), "https://files.example.com/images/nginx.tar.gz");
+            assert_eq!(
+                config.target_url("blobs/sha256/abc"),
+                "https://files.example.com/images/blobs/sha256/abc"
+            );
+        }
+    }
+}
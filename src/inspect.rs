@@ -0,0 +1,220 @@
+use crate::digest;
+use crate::download::resolve_manifest;
+use crate::registry::{parse_image_ref, RegistryClient};
+use crate::types::{
+    Descriptor, HistoryEntry, ImageConfig, ManifestListResponse, ManifestResponse, Platform,
+};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+/// 镜像审查报告
+#[derive(Debug, Serialize)]
+pub struct InspectReport {
+    pub image: String,
+    /// 引用解析出的 Manifest digest（多架构时为 Index 的 digest）
+    pub digest: String,
+    /// 选中平台的 Manifest digest
+    pub platform_digest: String,
+    pub config: ImageConfig,
+    pub layers: Vec<Descriptor>,
+    /// 压缩后的总大小（config + layers）
+    pub total_size: u64,
+    /// 多架构镜像中每个平台的大小
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platforms: Option<Vec<PlatformSummary>>,
+}
+
+/// 多架构镜像中单个平台的大小
+#[derive(Debug, Serialize)]
+pub struct PlatformSummary {
+    pub platform: Platform,
+    pub digest: String,
+    pub layers: usize,
+    pub total_size: u64,
+}
+
+/// 只获取 Manifest（或 Index）与 config，不下载 layers
+pub async fn inspect_image(
+    image_ref: &str,
+    username: Option<String>,
+    password: Option<String>,
+) -> Result<InspectReport> {
+    let image = parse_image_ref(image_ref)?;
+
+    let scope = format!("repository:{}:pull", image.repository);
+    let mut client = RegistryClient::new(image.registry.clone(), username, password)?;
+
+    eprintln!("Authenticating...");
+    client.authenticate(&image.repository, &scope).await?;
+
+    let resolved = resolve_manifest(&client, &image.repository, &image.reference).await?;
+    let manifest = &resolved.manifest;
+
+    eprintln!("Fetching config: {}", manifest.config.digest);
+    let config_bytes = client
+        .fetch_blob(&image.repository, &manifest.config.digest)
+        .await?
+        .bytes()
+        .await?;
+    if digest::sha256_digest(&config_bytes) != manifest.config.digest {
+        return Err(anyhow!("Config digest mismatch: {}", manifest.config.digest));
+    }
+    let config: ImageConfig = serde_json::from_slice(&config_bytes)?;
+
+    // 多架构时汇总每个平台的大小（只获取 Manifest）
+    let platforms = match &resolved.index {
+        Some(index) => {
+            let index: ManifestListResponse = serde_json::from_value(index.clone())?;
+            let mut platforms = Vec::new();
+            for entry in index.manifests {
+                // 跳过没有平台的条目与 BuildKit attestation manifest（unknown/unknown）
+                let Some(platform) = entry.platform.filter(|p| p.os != "unknown") else {
+                    continue;
+                };
+
+                let (_, child) = client.fetch_manifest(&image.repository, &entry.digest).await?;
+                let child: ManifestResponse = serde_json::from_value(child)?;
+                platforms.push(PlatformSummary {
+                    platform,
+                    digest: entry.digest,
+                    layers: child.layers.len(),
+                    total_size: child.config.size + child.layers.iter().map(|l| l.size).sum::<u64>(),
+                });
+            }
+            Some(platforms)
+        }
+        None => None,
+    };
+
+    Ok(InspectReport {
+        image: image_ref.to_string(),
+        digest: resolved.digest.clone(),
+        platform_digest: resolved.platform_digest.clone(),
+        total_size: manifest.config.size + manifest.layers.iter().map(|l| l.size).sum::<u64>(),
+        layers: manifest.layers.clone(),
+        config,
+        platforms,
+    })
+}
+
+/// 以表格形式输出审查报告
+pub fn print_report(report: &InspectReport) {
+    let config = report.config.config.clone().unwrap_or_default();
+
+    println!("Image:         {}", report.image);
+    println!("Digest:        {}", report.digest);
+    if report.platform_digest != report.digest {
+        println!("Manifest:      {}", report.platform_digest);
+    }
+    println!(
+        "Platform:      {}/{}{}",
+        report.config.os.as_deref().unwrap_or("unknown"),
+        report.config.architecture.as_deref().unwrap_or("unknown"),
+        report.config.variant.as_deref().map(|v| format!("/{}", v)).unwrap_or_default()
+    );
+    println!("Created:       {}", report.config.created.as_deref().unwrap_or("-"));
+    println!("User:          {}", config.user.as_deref().filter(|u| !u.is_empty()).unwrap_or("(root)"));
+    println!("Entrypoint:    {}", format_command(&config.entrypoint));
+    println!("Cmd:           {}", format_command(&config.cmd));
+    println!("WorkingDir:    {}", config.working_dir.as_deref().filter(|w| !w.is_empty()).unwrap_or("-"));
+
+    let mut ports: Vec<&str> = config
+        .exposed_ports
+        .iter()
+        .flat_map(|p| p.keys().map(String::as_str))
+        .collect();
+    ports.sort();
+    println!("ExposedPorts:  {}", if ports.is_empty() { "-".to_string() } else { ports.join(", ") });
+
+    println!("Env:");
+    for env in config.env.iter().flatten() {
+        println!("  {}", env);
+    }
+
+    println!("Labels:");
+    let mut labels: Vec<(&String, &String)> = config.labels.iter().flatten().collect();
+    labels.sort();
+    for (key, value) in labels {
+        println!("  {}={}", key, value);
+    }
+
+    println!();
+    println!("Layers ({}, total {}):", report.layers.len(), format_size(report.total_size));
+    println!("  {:>3}  {:>10}  DIGEST", "#", "SIZE");
+    for (idx, layer) in report.layers.iter().enumerate() {
+        println!("  {:>3}  {:>10}  {}", idx + 1, format_size(layer.size), layer.digest);
+    }
+
+    println!();
+    println!("History:");
+    println!("  {:<20}  {:>10}  CREATED BY", "CREATED", "SIZE");
+    for (entry, size) in history_with_sizes(&report.config.history, &report.layers) {
+        let created = entry.created.as_deref().unwrap_or("-");
+        println!(
+            "  {:<20}  {:>10}  {}",
+            created.get(..19).unwrap_or(created),
+            size.map(format_size).unwrap_or_else(|| "0 B".to_string()),
+            entry.created_by.as_deref().unwrap_or("").trim()
+        );
+    }
+
+    if let Some(platforms) = &report.platforms {
+        println!();
+        println!("Platforms:");
+        println!("  {:<20}  {:>6}  {:>10}  DIGEST", "PLATFORM", "LAYERS", "SIZE");
+        for summary in platforms {
+            let platform = format!(
+                "{}/{}{}",
+                summary.platform.os,
+                summary.platform.architecture,
+                summary.platform.variant.as_deref().map(|v| format!("/{}", v)).unwrap_or_default()
+            );
+            println!(
+                "  {:<20}  {:>6}  {:>10}  {}",
+                platform,
+                summary.layers,
+                format_size(summary.total_size),
+                summary.digest
+            );
+        }
+    }
+}
+
+/// 将 history 与 layers 对应：`empty_layer` 的条目没有对应的 layer
+fn history_with_sizes<'a>(
+    history: &'a [HistoryEntry],
+    layers: &[Descriptor],
+) -> Vec<(&'a HistoryEntry, Option<u64>)> {
+    let mut layer_sizes = layers.iter().map(|l| l.size);
+    history
+        .iter()
+        .map(|entry| {
+            let size = if entry.empty_layer { None } else { layer_sizes.next() };
+            (entry, size)
+        })
+        .collect()
+}
+
+fn format_command(command: &Option<Vec<String>>) -> String {
+    match command {
+        Some(args) if !args.is_empty() => serde_json::to_string(args).unwrap_or_default(),
+        _ => "-".to_string(),
+    }
+}
+
+/// 格式化字节数
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.2} {}", size, UNITS[unit])
+    }
+}
@@ -1,15 +1,75 @@
+mod artifact;
+mod cache;
+mod cas;
+mod compression;
+mod copy;
+mod cosign;
+mod delta;
+mod destination;
+mod digest;
 mod download;
+mod extract;
+mod ftp;
+mod http;
+mod inspect;
+mod notify;
+mod plan;
+mod push;
+mod referrers;
 mod registry;
+mod retention;
+mod rootfs;
+mod s3;
+mod sftp;
+mod sidecar;
+mod smtp;
+mod stream;
 mod tar;
+#[cfg(test)]
+mod test_server;
+mod tls;
 mod types;
+mod volume;
+mod webdav;
+mod xml;
 
 use anyhow::{anyhow, Result};
+use destination::Destination;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        Some("push") => run_push(&args[2..]).await,
+        Some("copy") => run_copy(&args[2..]).await,
+        Some("extract") => run_extract(&args[2..]).await,
+        Some("inspect") => run_inspect(&args[2..]).await,
+        Some("apply") => run_apply(&args[2..]).await,
+        Some("join") => run_join(&args[2..]),
+        Some("verify") => run_verify(&args[2..]),
+        Some("--dry-run") => run_download(true, &mut notify::Report::default()).await,
+        Some(command) => Err(anyhow!("Unknown command: {}", command)),
+        None if env_flag("DRY_RUN") => run_download(true, &mut notify::Report::default()).await,
+        None => {
+            let started = std::time::Instant::now();
+            let mut report = notify::Report::new(&env::var("IMAGE_REF").unwrap_or_default());
+            let result = run_download(false, &mut report).await;
+            report.finish(started.elapsed(), result.as_ref().err());
+            notify::notify(&report).await;
+            result
+        }
+    }
+}
+
+/// 下载镜像并打包成 tar.gz；`dry_run` 时只输出传输计划
+///
+/// 归档信息与上传路径记录到 `report`，用于运行结束后的通知。
+async fn run_download(dry_run: bool, report: &mut notify::Report) -> Result<()> {
     // 从环境变量获取配置
     let image_ref = env::var("IMAGE_REF")
         .map_err(|_| anyhow!("IMAGE_REF environment variable is required"))?;
@@ -17,8 +77,186 @@ async fn main() -> Result<()> {
     let username = env::var("DOCKER_HUB_USERNAME").ok();
     let password = env::var("DOCKER_HUB_TOKEN").ok();
 
-    let output_dir = PathBuf::from("/mnt/download");
-    let tar_output_dir = PathBuf::from("/mnt");
+    // cosign 公钥文件（PEM），设置后必须通过签名校验才会打包
+    let cosign_public_key = match env::var("COSIGN_PUBLIC_KEY") {
+        Ok(path) => Some(
+            fs::read_to_string(&path)
+                .await
+                .map_err(|e| anyhow!("Failed to read COSIGN_PUBLIC_KEY {}: {}", path, e))?,
+        ),
+        Err(_) => None,
+    };
+    // 需要一并下载的 referrer 制品类型（逗号分隔，`*` 表示全部）
+    let referrer_artifact_types = env::var("REFERRER_ARTIFACT_TYPES")
+        .map(|v| v.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+        .unwrap_or_default();
+
+    // 本地 Blob 缓存目录
+    let cache = env::var("BLOB_CACHE_DIR")
+        .ok()
+        .map(|dir| cache::BlobCache::new(PathBuf::from(dir)));
+
+    if dry_run {
+        // 内容寻址模式下目标已有的 Blob 不需要传输
+        let destinations = if env_flag("REMOTE_CAS") {
+            destination::from_env()?
+        } else {
+            Vec::new()
+        };
+        let blob_store = cas::BlobStore::from_env()?;
+        let plan = plan::plan_download(
+            &image_ref,
+            username,
+            password,
+            cache.as_ref(),
+            blob_store.as_ref(),
+            &destinations,
+        ).await?;
+        plan::print_summary(&plan);
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
+
+    // 增量模式：基础镜像引用或上次运行保存的 manifest.json
+    let delta_base = env::var("DELTA_BASE").ok().filter(|b| !b.is_empty());
+    let gzip_layers = env::var("LAYER_COMPRESSION").is_ok_and(|v| v == "gzip");
+    let skip_blobs = match &delta_base {
+        Some(_) if gzip_layers || env_flag("EXPORT_ROOTFS") || env_flag("ARTIFACT_EXTRACT") => {
+            return Err(anyhow!(
+                "DELTA_BASE cannot be combined with LAYER_COMPRESSION, EXPORT_ROOTFS or ARTIFACT_EXTRACT"
+            ));
+        }
+        Some(base) => delta::base_blobs(base, username.clone(), password.clone()).await?,
+        None => Default::default(),
+    };
+
+    let options = download::DownloadOptions {
+        cosign_public_key,
+        referrer_artifact_types,
+        gzip_layers,
+        cache,
+        blob_store: cas::BlobStore::from_env()?,
+        skip_blobs,
+    };
+
+    // 分卷大小（如 `4095M`），设置后归档切分为 `.001`、`.002` ……
+    let volume_size = match env::var("VOLUME_SIZE") {
+        Ok(size) if !size.is_empty() => Some(volume::parse_size(&size)?),
+        _ => None,
+    };
+
+    // 内容寻址模式：逐个 Blob 上传到目标，不打包归档
+    let remote_cas = env_flag("REMOTE_CAS");
+    if remote_cas
+        && (env_flag("STREAM_UPLOAD")
+            || delta_base.is_some()
+            || env_flag("EXPORT_ROOTFS")
+            || env_flag("ARTIFACT_EXTRACT")
+            || !options.referrer_artifact_types.is_empty()
+            || volume_size.is_some())
+    {
+        return Err(anyhow!(
+            "REMOTE_CAS cannot be combined with STREAM_UPLOAD, DELTA_BASE, EXPORT_ROOTFS, ARTIFACT_EXTRACT, REFERRER_ARTIFACT_TYPES or VOLUME_SIZE"
+        ));
+    }
+
+    // 流式模式：不在本地保存 layers 与归档，直接上传
+    if env_flag("STREAM_UPLOAD") {
+        if options.gzip_layers
+            || delta_base.is_some()
+            || env_flag("EXPORT_ROOTFS")
+            || env_flag("ARTIFACT_EXTRACT")
+            || !options.referrer_artifact_types.is_empty()
+            || volume_size.is_some()
+        {
+            return Err(anyhow!(
+                "STREAM_UPLOAD cannot be combined with LAYER_COMPRESSION, DELTA_BASE, EXPORT_ROOTFS, ARTIFACT_EXTRACT, REFERRER_ARTIFACT_TYPES or VOLUME_SIZE"
+            ));
+        }
+
+        let archive_name = format!("{}.tar.gz", tar::sanitize_filename(&image_ref));
+        let destinations = destination::from_env()?;
+        eprintln!("\n📤 Streaming {} to destinations...", archive_name);
+        let summary = stream::stream_image(
+            &image_ref,
+            username,
+            password,
+            options.cosign_public_key.as_deref(),
+            &archive_name,
+            destinations.clone(),
+        ).await?;
+
+        eprintln!("\n✅ Streamed {} ({} bytes, {})", archive_name, summary.size, summary.digest);
+        for destination in &summary.destinations {
+            eprintln!("  - {}", destination);
+        }
+        report.filename = Some(archive_name.clone());
+        report.digest = Some(summary.digest.clone());
+        report.manifest_digest = Some(summary.manifest_digest.clone());
+        report.size = Some(summary.size);
+        report.destinations = summary.destinations.clone();
+
+        // 校验和与元数据只写入临时目录，随后上传到每个目标
+        let metadata = sidecar::ArchiveMetadata::new(
+            &image_ref,
+            &summary.manifest_digest,
+            &summary.platform_digest,
+            &summary.manifest,
+            summary.platform.clone(),
+            sidecar::ArchiveInfo {
+                name: archive_name.clone(),
+                size: summary.size,
+                digest: summary.digest.clone(),
+                volumes: None,
+            },
+        );
+        let sidecar_dir = env::temp_dir().join(format!("sidecars-{}", std::process::id()));
+        fs::create_dir_all(&sidecar_dir).await?;
+        let result = async {
+            let sidecars = sidecar::write_sidecars(&sidecar_dir, &[], &metadata)?;
+            report.checksum = std::fs::read_to_string(&sidecars[0]).ok();
+            for destination in &destinations {
+                for file in &sidecars {
+                    destination.put(file, &destination::file_name(file)?).await?;
+                }
+                if let Some(policy) = destination.retention() {
+                    if let Err(e) = retention::apply(destination.as_ref(), policy, &archive_name).await {
+                        eprintln!("⚠️  Retention failed on {}: {:#}", destination.location(), e);
+                    }
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        let _ = fs::remove_dir_all(&sidecar_dir).await;
+        result?;
+
+        if let Ok(github_output) = env::var("GITHUB_OUTPUT") {
+            use std::io::Write;
+            if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&github_output) {
+                writeln!(file, "archive_name={}", archive_name)?;
+                writeln!(file, "archive_size={}", summary.size)?;
+                writeln!(file, "archive_digest={}", summary.digest)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    // 本地输出目录（默认 `/mnt`），layers 下载到其中的 `download/`
+    let tar_output_dir = env::var("OUTPUT_DIR")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .map_or_else(|| PathBuf::from("/mnt"), PathBuf::from);
+    let output_dir = tar_output_dir.join("download");
+
+    // 下载前解析上传目标，配置错误时尽早失败
+    let destinations = destination::from_env()?;
+    if remote_cas && destinations.is_empty() {
+        return Err(anyhow!(
+            "REMOTE_CAS requires DESTINATIONS, FTP_SERVER, SFTP_SERVER, S3_BUCKET or HTTP_UPLOAD_URL"
+        ));
+    }
 
     eprintln!("========================================");
     eprintln!("Docker Image Downloader");
@@ -33,24 +271,71 @@ async fn main() -> Result<()> {
     }
     fs::create_dir_all(&output_dir).await?;
 
+    // 制品模式：按 title 注解直接输出文件，不打包镜像归档
+    if env_flag("ARTIFACT_EXTRACT") {
+        eprintln!("\n📥 Extracting artifact...");
+        let extracted_files = artifact::extract_artifact(
+            &image_ref,
+            username,
+            password,
+            &output_dir,
+            &options,
+        ).await?;
+
+        eprintln!("\n✅ Artifact extracted to: {}", output_dir.display());
+        report.destinations.push(output_dir.display().to_string());
+        for file in &extracted_files {
+            eprintln!("  - {}", file);
+        }
+
+        if let Ok(github_output) = env::var("GITHUB_OUTPUT") {
+            use std::io::Write;
+            if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&github_output) {
+                writeln!(file, "artifact_dir={}", output_dir.display())?;
+            }
+        }
+
+        return Ok(());
+    }
+
     // 下载镜像
     eprintln!("\n📥 Starting download...");
-    let downloaded_files = download::download_image(
+    let downloaded = download::download_image(
         &image_ref,
         username,
         password,
         &output_dir,
+        &options,
     ).await?;
 
     eprintln!("\n✅ Download completed!");
-    eprintln!("Downloaded {} files:", downloaded_files.len());
-    for file in &downloaded_files {
+    eprintln!("Downloaded {} files:", downloaded.files.len());
+    for file in &downloaded.files {
         eprintln!("  - {}", file);
     }
 
-    // 打包成 tar.gz
-    eprintln!("\n📦 Creating tar archive...");
-    let tar_filename = format!("{}.tar.gz", tar::sanitize_filename(&image_ref));
+    report.manifest_digest = Some(downloaded.digest.clone());
+    if remote_cas {
+        report.destinations =
+            upload_blobs(&destinations, &image_ref, &output_dir, &downloaded.manifest).await?;
+        return Ok(());
+    }
+
+    if let Some(base) = &delta_base {
+        let reused_bytes =
+            delta::write_delta_manifest(&output_dir, &image_ref, base, &options.skip_blobs).await?;
+        eprintln!("Reused from base: {:.2} MB", reused_bytes as f64 / (1024.0 * 1024.0));
+    }
+
+    // 打包成 tar.gz（导出模式下输出合并后的根文件系统）
+    let export_rootfs = env_flag("EXPORT_ROOTFS");
+    let tar_filename = if export_rootfs {
+        format!("{}.rootfs.tar.gz", tar::sanitize_filename(&image_ref))
+    } else if delta_base.is_some() {
+        format!("{}.delta.tar.gz", tar::sanitize_filename(&image_ref))
+    } else {
+        format!("{}.tar.gz", tar::sanitize_filename(&image_ref))
+    };
     let tar_path = tar_output_dir.join(&tar_filename);
 
     // 删除旧的 tar 文件（如果存在）
@@ -58,24 +343,387 @@ async fn main() -> Result<()> {
         fs::remove_file(&tar_path).await?;
     }
 
-    tar::create_tar_archive(&output_dir, &tar_path)?;
+    let files = if export_rootfs {
+        eprintln!("\n📦 Exporting root filesystem...");
+        rootfs::export_rootfs(&output_dir, &tar_path, volume_size)?
+    } else {
+        eprintln!("\n📦 Creating tar archive...");
+        tar::create_tar_archive(&output_dir, &tar_path, volume_size)?
+    };
 
-    // 显示文件大小
-    let metadata = fs::metadata(&tar_path).await?;
-    let size_mb = metadata.len() as f64 / (1024.0 * 1024.0);
+    // 校验和与元数据（增量模式下 config 可能不在归档中）
+    let platform = std::fs::read(output_dir.join("blobs").join(&downloaded.manifest.config.digest))
+        .ok()
+        .and_then(|config| sidecar::platform_of(&config));
+    let metadata = sidecar::ArchiveMetadata::new(
+        &image_ref,
+        &downloaded.digest,
+        &downloaded.platform_digest,
+        &downloaded.manifest,
+        platform,
+        sidecar::archive_info(&tar_filename, &files)?,
+    );
+    let sidecars = sidecar::write_sidecars(&tar_output_dir, &files, &metadata)?;
+    report.checksum = std::fs::read_to_string(&sidecars[0]).ok();
+
+    // 显示文件大小（分卷模式下为所有分卷之和）
+    let size_mb = metadata.archive.size as f64 / (1024.0 * 1024.0);
     eprintln!("Archive size: {:.2} MB", size_mb);
+    eprintln!("Archive digest: {}", metadata.archive.digest);
 
-    eprintln!("\n✅ All done! Archive saved to: {}", tar_path.display());
+    // 分卷模式下以索引文件作为归档路径
+    let archive_path = files.last().cloned().unwrap_or(tar_path);
+    eprintln!("\n✅ All done! Archive saved to: {}", archive_path.display());
+    report.filename = Some(tar_filename.clone());
+    report.digest = Some(metadata.archive.digest.clone());
+    report.size = Some(metadata.archive.size);
+    report.destinations.push(archive_path.display().to_string());
 
     // 设置 GitHub Actions 输出
     if let Ok(github_output) = env::var("GITHUB_OUTPUT") {
         use std::io::Write;
         if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&github_output) {
-            writeln!(file, "archive_path={}", tar_path.display())?;
+            writeln!(file, "archive_path={}", archive_path.display())?;
             writeln!(file, "archive_name={}", tar_filename)?;
-            writeln!(file, "archive_size={}", metadata.len())?;
+            writeln!(file, "archive_size={}", metadata.archive.size)?;
+            writeln!(file, "archive_digest={}", metadata.archive.digest)?;
+            if let Some(volumes) = metadata.archive.volumes {
+                writeln!(file, "volume_count={}", volumes)?;
+            }
+        }
+    }
+
+    let remote_paths = upload_archive(&destinations, &tar_filename, &files, &sidecars).await?;
+    report.destinations.extend(remote_paths);
+
+    Ok(())
+}
+
+/// 将归档上传到所有目标（`FTP_SERVER` 等环境变量以及 `DESTINATIONS` 中的 URL）
+///
+/// 分卷模式下依次上传所有分卷，索引文件在分卷之后上传；输出中记录其中最后一个文件的远程路径。
+/// 校验和与元数据文件在归档之后上传，接收端看到它们时归档已经完整。
+/// 配置了保留策略的目标在上传成功后清理旧归档，清理失败只输出警告。返回每个目标的远程路径。
+async fn upload_archive(
+    destinations: &[Arc<dyn Destination>],
+    archive_name: &str,
+    files: &[PathBuf],
+    sidecars: &[PathBuf],
+) -> Result<Vec<String>> {
+    let mut remote_paths = Vec::new();
+    for destination in destinations {
+        eprintln!("\n📤 Uploading to {}...", destination.location());
+        let mut remote_path = String::new();
+        for file in files {
+            remote_path = destination.put(file, &destination::file_name(file)?).await?;
+            eprintln!("✅ Upload completed: {}", remote_path);
+        }
+        for file in sidecars {
+            destination.put(file, &destination::file_name(file)?).await?;
+        }
+
+        if let Ok(github_output) = env::var("GITHUB_OUTPUT") {
+            use std::io::Write;
+            if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&github_output) {
+                writeln!(file, "{}={}", destination.output_key(), remote_path)?;
+            }
+        }
+
+        if let Some(policy) = destination.retention() {
+            if let Err(e) = retention::apply(destination.as_ref(), policy, archive_name).await {
+                eprintln!("⚠️  Retention failed on {}: {:#}", destination.location(), e);
+            }
+        }
+        remote_paths.push(remote_path);
+    }
+
+    Ok(remote_paths)
+}
+
+/// 将下载目录中的 Blob 与 Index 上传到所有目标的内容寻址存储，返回每个目标上 Index 的路径
+async fn upload_blobs(
+    destinations: &[Arc<dyn Destination>],
+    image_ref: &str,
+    image_dir: &Path,
+    manifest: &types::ManifestResponse,
+) -> Result<Vec<String>> {
+    let mut indexes = Vec::new();
+    for destination in destinations {
+        eprintln!("\n📤 Uploading blobs to {}...", destination.location());
+        let summary = cas::upload_image(destination.as_ref(), image_ref, image_dir, manifest).await?;
+        eprintln!(
+            "✅ Uploaded {} blobs ({:.2} MB), skipped {} present ({:.2} MB)",
+            summary.uploaded,
+            summary.uploaded_bytes as f64 / (1024.0 * 1024.0),
+            summary.skipped,
+            summary.skipped_bytes as f64 / (1024.0 * 1024.0)
+        );
+        eprintln!("Index: {}", summary.index);
+
+        if let Ok(github_output) = env::var("GITHUB_OUTPUT") {
+            use std::io::Write;
+            if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&github_output) {
+                writeln!(file, "{}={}", destination.output_key(), summary.index)?;
+                writeln!(file, "uploaded_bytes={}", summary.uploaded_bytes)?;
+                writeln!(file, "skipped_bytes={}", summary.skipped_bytes)?;
+            }
+        }
+        indexes.push(summary.index);
+    }
+
+    Ok(indexes)
+}
+
+/// 读取布尔型环境变量（`true`/`1` 为真）
+fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|v| v == "true" || v == "1")
+}
+
+/// 将下载目录或归档推送到目标 registry
+///
+/// 用法: `push <source> <target-ref>`
+async fn run_push(args: &[String]) -> Result<()> {
+    let (source, target_ref) = match args {
+        [source, target_ref] => (PathBuf::from(source), target_ref),
+        _ => return Err(anyhow!("Usage: push <source-dir-or-archive> <target-ref>")),
+    };
+
+    let username = env::var("TARGET_REGISTRY_USERNAME").ok();
+    let password = env::var("TARGET_REGISTRY_PASSWORD").ok();
+    let mount_from = env::var("PUSH_MOUNT_FROM").ok();
+
+    eprintln!("========================================");
+    eprintln!("Docker Image Pusher");
+    eprintln!("========================================");
+    eprintln!("Source: {}", source.display());
+    eprintln!("Target: {}", target_ref);
+    eprintln!("========================================");
+
+    let digest = push::push_image(
+        &source,
+        target_ref,
+        username,
+        password,
+        mount_from.as_deref(),
+    ).await?;
+
+    eprintln!("\n✅ Push completed! Digest: {}", digest);
+
+    if let Ok(github_output) = env::var("GITHUB_OUTPUT") {
+        use std::io::Write;
+        if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&github_output) {
+            writeln!(file, "pushed_digest={}", digest)?;
         }
     }
 
     Ok(())
 }
+
+/// 在两个 registry 之间直接复制镜像
+///
+/// 用法: `copy <src-ref> <dst-ref> [--all-platforms] [--dry-run]`
+async fn run_copy(args: &[String]) -> Result<()> {
+    let all_platforms = args.iter().any(|a| a == "--all-platforms");
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+    let refs: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    let (src_ref, dst_ref) = match refs.as_slice() {
+        [src_ref, dst_ref] => (src_ref.as_str(), dst_ref.as_str()),
+        _ => return Err(anyhow!("Usage: copy <src-ref> <dst-ref> [--all-platforms] [--dry-run]")),
+    };
+
+    let src_credentials = (
+        env::var("DOCKER_HUB_USERNAME").ok(),
+        env::var("DOCKER_HUB_TOKEN").ok(),
+    );
+    let dst_credentials = (
+        env::var("TARGET_REGISTRY_USERNAME").ok(),
+        env::var("TARGET_REGISTRY_PASSWORD").ok(),
+    );
+
+    eprintln!("========================================");
+    eprintln!("Docker Image Copier");
+    eprintln!("========================================");
+    eprintln!("Source: {}", src_ref);
+    eprintln!("Target: {}", dst_ref);
+    eprintln!("All platforms: {}", all_platforms);
+    eprintln!("========================================");
+
+    if dry_run {
+        let plan = plan::plan_copy(
+            src_ref,
+            dst_ref,
+            src_credentials,
+            dst_credentials,
+            all_platforms,
+        ).await?;
+        plan::print_summary(&plan);
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
+
+    let digest = copy::copy_image(
+        src_ref,
+        dst_ref,
+        src_credentials,
+        dst_credentials,
+        all_platforms,
+    ).await?;
+
+    eprintln!("\n✅ Copy completed! Digest: {}", digest);
+
+    if let Ok(github_output) = env::var("GITHUB_OUTPUT") {
+        use std::io::Write;
+        if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&github_output) {
+            writeln!(file, "pushed_digest={}", digest)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 从镜像中提取指定路径的文件
+///
+/// 用法: `extract <image-ref> <output-dir> <path-glob>...`
+async fn run_extract(args: &[String]) -> Result<()> {
+    let (image_ref, output_dir, patterns) = match args {
+        [image_ref, output_dir, patterns @ ..] if !patterns.is_empty() => {
+            (image_ref, PathBuf::from(output_dir), patterns)
+        }
+        _ => return Err(anyhow!("Usage: extract <image-ref> <output-dir> <path-glob>...")),
+    };
+
+    let username = env::var("DOCKER_HUB_USERNAME").ok();
+    let password = env::var("DOCKER_HUB_TOKEN").ok();
+
+    eprintln!("========================================");
+    eprintln!("Docker Image Extractor");
+    eprintln!("========================================");
+    eprintln!("Image: {}", image_ref);
+    eprintln!("Paths: {}", patterns.join(", "));
+    eprintln!("Output: {}", output_dir.display());
+    eprintln!("========================================");
+
+    fs::create_dir_all(&output_dir).await?;
+
+    let extracted_files = extract::extract_paths(
+        image_ref,
+        username,
+        password,
+        patterns,
+        &output_dir,
+    ).await?;
+
+    eprintln!("\n✅ Extracted {} entries:", extracted_files.len());
+    for file in &extracted_files {
+        eprintln!("  - {}", file);
+    }
+
+    Ok(())
+}
+
+/// 由基础镜像归档与增量包组装完整的镜像归档
+///
+/// 用法: `apply <base-archive> <delta-archive> <output-archive>`
+async fn run_apply(args: &[String]) -> Result<()> {
+    let (base, delta_path, output_file) = match args {
+        [base, delta_path, output_file] => (
+            PathBuf::from(base),
+            PathBuf::from(delta_path),
+            PathBuf::from(output_file),
+        ),
+        _ => return Err(anyhow!("Usage: apply <base-archive> <delta-archive> <output-archive>")),
+    };
+
+    eprintln!("========================================");
+    eprintln!("Docker Image Delta Apply");
+    eprintln!("========================================");
+    eprintln!("Base: {}", base.display());
+    eprintln!("Delta: {}", delta_path.display());
+    eprintln!("Output: {}", output_file.display());
+    eprintln!("========================================");
+
+    delta::apply_delta(&base, &delta_path, &output_file).await?;
+
+    eprintln!("\n✅ Image assembled: {}", output_file.display());
+
+    if let Ok(github_output) = env::var("GITHUB_OUTPUT") {
+        use std::io::Write;
+        if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&github_output) {
+            writeln!(file, "archive_path={}", output_file.display())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 校验并拼接分卷，还原完整归档
+///
+/// 用法: `join <index-file> [output-archive]`，默认输出到索引所在目录下的原归档文件名
+fn run_join(args: &[String]) -> Result<()> {
+    let (index_file, output_file) = match args {
+        [index_file] => {
+            let index_file = PathBuf::from(index_file);
+            let index = volume::read_index(&index_file)?;
+            let output_file = index_file.parent().unwrap_or(std::path::Path::new(".")).join(index.archive);
+            (index_file, output_file)
+        }
+        [index_file, output_file] => (PathBuf::from(index_file), PathBuf::from(output_file)),
+        _ => return Err(anyhow!("Usage: join <index-file> [output-archive]")),
+    };
+
+    let size = volume::join(&index_file, &output_file)?;
+    eprintln!("\n✅ Archive joined: {} ({} bytes)", output_file.display(), size);
+
+    if let Ok(github_output) = env::var("GITHUB_OUTPUT") {
+        use std::io::Write;
+        if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&github_output) {
+            writeln!(file, "archive_path={}", output_file.display())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 校验所有分卷的大小与 digest，不写入任何文件
+///
+/// 用法: `verify <index-file>`
+fn run_verify(args: &[String]) -> Result<()> {
+    let [index_file] = args else {
+        return Err(anyhow!("Usage: verify <index-file>"));
+    };
+
+    let index = volume::verify(std::path::Path::new(index_file))?;
+    eprintln!(
+        "\n✅ {} volumes verified ({}, {} bytes)",
+        index.volumes.len(),
+        index.archive,
+        index.size
+    );
+
+    Ok(())
+}
+
+/// 查看镜像 config 与历史，不下载 layers
+///
+/// 用法: `inspect <image-ref> [--json]`
+async fn run_inspect(args: &[String]) -> Result<()> {
+    let json = args.iter().any(|a| a == "--json");
+    let refs: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    let image_ref = match refs.as_slice() {
+        [image_ref] => image_ref.as_str(),
+        _ => return Err(anyhow!("Usage: inspect <image-ref> [--json]")),
+    };
+
+    let username = env::var("DOCKER_HUB_USERNAME").ok();
+    let password = env::var("DOCKER_HUB_TOKEN").ok();
+
+    let report = inspect::inspect_image(image_ref, username, password).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        inspect::print_report(&report);
+    }
+
+    Ok(())
+}
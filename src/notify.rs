@@ -0,0 +1,428 @@
+use crate::registry::parse_image_ref;
+use crate::smtp::{self, SmtpConfig};
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Url};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::time::Duration;
+
+/// 传输报告，所有通知目标使用同一份数据
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    /// `success` 或 `failure`
+    pub status: &'static str,
+    pub image: String,
+    /// 仓库的第一段（Docker Hub 官方镜像为 `library`），兼容旧的通知格式
+    pub organization: String,
+    pub name: String,
+    pub tag: String,
+    /// 归档文件名
+    pub filename: Option<String>,
+    /// 归档的 sha256 digest
+    pub digest: Option<String>,
+    /// 引用解析出的 Manifest digest
+    pub manifest_digest: Option<String>,
+    /// 归档大小（分卷时为总大小）
+    pub size: Option<u64>,
+    /// 本地归档路径与每个目标的远程路径
+    pub destinations: Vec<String>,
+    pub duration_secs: u64,
+    pub error: Option<String>,
+    /// 校验和文件内容（`sha256sum -c` 格式）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+impl Report {
+    pub fn new(image_ref: &str) -> Self {
+        let mut report = Self {
+            image: image_ref.to_string(),
+            ..Default::default()
+        };
+        if let Ok(image) = parse_image_ref(image_ref) {
+            let (organization, name) = image
+                .repository
+                .split_once('/')
+                .unwrap_or(("library", &image.repository));
+            report.organization = organization.to_string();
+            report.name = name.to_string();
+            report.tag = image.reference;
+        }
+        report
+    }
+
+    /// 记录耗时与结果
+    pub fn finish(&mut self, duration: Duration, error: Option<&anyhow::Error>) {
+        self.duration_secs = duration.as_secs();
+        self.status = if error.is_some() { "failure" } else { "success" };
+        self.error = error.map(|e| format!("{:#}", e));
+    }
+
+    pub fn success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    pub fn title(&self) -> String {
+        if self.success() {
+            format!("✅ Image transfer succeeded: {}", self.image)
+        } else {
+            format!("❌ Image transfer failed: {}", self.image)
+        }
+    }
+
+    /// 报告中的各项（名称、值），未知的项不列出
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![("Image", self.image.clone())];
+        if let Some(filename) = &self.filename {
+            fields.push(("Archive", filename.clone()));
+        }
+        if let Some(digest) = &self.digest {
+            fields.push(("Digest", digest.clone()));
+        }
+        if let Some(size) = self.size {
+            fields.push(("Size", format!("{:.2} MB", size as f64 / (1024.0 * 1024.0))));
+        }
+        for destination in &self.destinations {
+            fields.push(("Destination", destination.clone()));
+        }
+        fields.push(("Duration", format_duration(self.duration_secs)));
+        if let Some(error) = &self.error {
+            fields.push(("Error", error.clone()));
+        }
+        fields
+    }
+
+    /// 纯文本消息
+    pub fn text(&self) -> String {
+        let mut text = self.title();
+        for (name, value) in self.fields() {
+            text.push_str(&format!("\n{}: {}", name, value));
+        }
+        text
+    }
+
+    /// Markdown 消息（钉钉、企业微信、Slack）
+    pub fn markdown(&self) -> String {
+        let mut text = format!("### {}\n", self.title());
+        for (name, value) in self.fields() {
+            text.push_str(&format!("\n- **{}**: `{}`", name, value));
+        }
+        text
+    }
+}
+
+/// 通知目标
+#[derive(Debug, Clone)]
+pub enum Notifier {
+    /// 钉钉机器人，设置加签密钥时在 URL 中附加 `timestamp` 与 `sign`
+    DingTalk { url: String, secret: Option<String> },
+    /// 企业微信群机器人
+    WeCom { url: String },
+    /// 飞书机器人，设置签名校验密钥时在消息体中附加 `timestamp` 与 `sign`
+    Feishu { url: String, secret: Option<String> },
+    /// Slack Incoming Webhook
+    Slack { url: String },
+    /// 通用 Webhook：POST 整个报告（JSON）
+    Webhook { url: String },
+    /// SMTP 邮件
+    Email(SmtpConfig),
+}
+
+impl Notifier {
+    /// 从环境变量读取所有通知目标
+    pub fn from_env() -> Result<Vec<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let mut notifiers = Vec::new();
+        if let Some(url) = var("DINGTALK_WEBHOOK") {
+            notifiers.push(Self::DingTalk {
+                url,
+                secret: var("DINGTALK_SECRET"),
+            });
+        }
+        if let Some(url) = var("WECOM_WEBHOOK") {
+            notifiers.push(Self::WeCom { url });
+        }
+        if let Some(url) = var("FEISHU_WEBHOOK") {
+            notifiers.push(Self::Feishu {
+                url,
+                secret: var("FEISHU_SECRET"),
+            });
+        }
+        if let Some(url) = var("SLACK_WEBHOOK") {
+            notifiers.push(Self::Slack { url });
+        }
+        if let Some(url) = var("NOTIFICATION_URL") {
+            notifiers.push(Self::Webhook { url });
+        }
+        if let Some(config) = SmtpConfig::from_env()? {
+            notifiers.push(Self::Email(config));
+        }
+        Ok(notifiers)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DingTalk { .. } => "DingTalk",
+            Self::WeCom { .. } => "WeCom",
+            Self::Feishu { .. } => "Feishu",
+            Self::Slack { .. } => "Slack",
+            Self::Webhook { .. } => "webhook",
+            Self::Email(_) => "Email",
+        }
+    }
+
+    /// 发送报告
+    pub async fn send(&self, report: &Report) -> Result<()> {
+        let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+        let timestamp = chrono::Utc::now().timestamp_millis();
+
+        let (url, body) = match self {
+            Self::DingTalk { url, secret } => {
+                let mut url = Url::parse(url).map_err(|e| anyhow!("Invalid DingTalk webhook: {}", e))?;
+                if let Some(secret) = secret {
+                    // 签名：HmacSHA256(secret, "<毫秒时间戳>\n<secret>")
+                    let sign = sign(secret.as_bytes(), format!("{}\n{}", timestamp, secret).as_bytes());
+                    url.query_pairs_mut()
+                        .append_pair("timestamp", &timestamp.to_string())
+                        .append_pair("sign", &sign);
+                }
+                let body = json!({
+                    "msgtype": "markdown",
+                    "markdown": { "title": report.title(), "text": report.markdown() },
+                });
+                (url, body)
+            }
+            Self::WeCom { url } => {
+                let body = json!({
+                    "msgtype": "markdown",
+                    "markdown": { "content": report.markdown() },
+                });
+                (Url::parse(url).map_err(|e| anyhow!("Invalid WeCom webhook: {}", e))?, body)
+            }
+            Self::Feishu { url, secret } => {
+                let mut body = json!({
+                    "msg_type": "text",
+                    "content": { "text": report.text() },
+                });
+                if let Some(secret) = secret {
+                    // 签名：以 "<秒级时间戳>\n<secret>" 为密钥对空消息计算 HmacSHA256
+                    let timestamp = timestamp / 1000;
+                    body["timestamp"] = json!(timestamp.to_string());
+                    body["sign"] = json!(sign(format!("{}\n{}", timestamp, secret).as_bytes(), b""));
+                }
+                (Url::parse(url).map_err(|e| anyhow!("Invalid Feishu webhook: {}", e))?, body)
+            }
+            Self::Slack { url } => {
+                let mut text = format!("*{}*", report.title());
+                for (name, value) in report.fields() {
+                    text.push_str(&format!("\n• {}: `{}`", name, value));
+                }
+                (Url::parse(url).map_err(|e| anyhow!("Invalid Slack webhook: {}", e))?, json!({ "text": text }))
+            }
+            Self::Webhook { url } => (
+                Url::parse(url).map_err(|e| anyhow!("Invalid NOTIFICATION_URL: {}", e))?,
+                serde_json::to_value(report)?,
+            ),
+            Self::Email(config) => {
+                return smtp::send(config, report)
+                    .await
+                    .map_err(|e| e.context("Email notification failed"));
+            }
+        };
+
+        let response = client.post(url).json(&body).send().await?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(anyhow!("{} notification failed: {} - {}", self.name(), status, text));
+        }
+
+        // 钉钉、企业微信与飞书出错时仍返回 200，错误码在响应体中
+        if let Ok(reply) = serde_json::from_str::<Value>(&text) {
+            let code = ["errcode", "code", "StatusCode"]
+                .iter()
+                .find_map(|key| reply.get(*key).and_then(Value::as_i64));
+            if let Some(code) = code.filter(|c| *c != 0) {
+                let message = ["errmsg", "msg", "StatusMessage"]
+                    .iter()
+                    .find_map(|key| reply.get(*key).and_then(Value::as_str))
+                    .unwrap_or_default();
+                return Err(anyhow!("{} notification failed: {} {}", self.name(), code, message));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 向所有配置的目标发送报告，失败只输出警告
+///
+/// `NOTIFY_ON` 为 `success` 或 `failure` 时只在对应结果下发送（默认都发送）。
+pub async fn notify(report: &Report) {
+    let notifiers = match Notifier::from_env() {
+        Ok(notifiers) => notifiers,
+        Err(e) => {
+            eprintln!("⚠️  Invalid notification settings: {:#}", e);
+            return;
+        }
+    };
+    if notifiers.is_empty() {
+        return;
+    }
+
+    let send = match std::env::var("NOTIFY_ON").as_deref() {
+        Ok("success") => report.success(),
+        Ok("failure") => !report.success(),
+        _ => true,
+    };
+    if !send {
+        return;
+    }
+
+    for notifier in notifiers {
+        match notifier.send(report).await {
+            Ok(()) => eprintln!("📨 {} notification sent", notifier.name()),
+            Err(e) => eprintln!("⚠️  {:#}", e),
+        }
+    }
+}
+
+/// HmacSHA256 签名（Base64）
+fn sign(key: &[u8], message: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(message);
+    base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// `1h 2m 3s` 格式的耗时
+fn format_duration(secs: u64) -> String {
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, s) => format!("{}h {}m {}s", h, m, s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+
+    fn report() -> Report {
+        let mut report = Report::new("nginx:1.27");
+        report.filename = Some("nginx_1.27.tar.gz".to_string());
+        report.finish(Duration::from_secs(65), None);
+        report
+    }
+
+    fn body(server: &TestServer) -> Value {
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].header("content-type"), Some("application/json"));
+        serde_json::from_slice(&requests[0].body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn dingtalk_signs_timestamp_in_query() {
+        let server = TestServer::start(|_| Response::ok(r#"{"errcode":0,"errmsg":"ok"}"#)).await;
+        let notifier = Notifier::DingTalk {
+            url: format!("{}/robot/send?access_token=abc", server.url),
+            secret: Some("SEC123".to_string()),
+        };
+        notifier.send(&report()).await.unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(request.path(), "/robot/send");
+        assert_eq!(request.query("access_token").as_deref(), Some("abc"));
+        let timestamp = request.query("timestamp").unwrap();
+        let expected = sign(b"SEC123", format!("{}\nSEC123", timestamp).as_bytes());
+        assert_eq!(request.query("sign"), Some(expected));
+
+        let body = body(&server);
+        assert_eq!(body["msgtype"], "markdown");
+        assert_eq!(body["markdown"]["title"], "✅ Image transfer succeeded: nginx:1.27");
+        assert!(body["markdown"]["text"].as_str().unwrap().contains("**Duration**: `1m 5s`"));
+    }
+
+    #[tokio::test]
+    async fn feishu_signs_timestamp_in_body() {
+        let server = TestServer::start(|_| Response::ok(r#"{"code":0,"msg":"success"}"#)).await;
+        let notifier = Notifier::Feishu {
+            url: format!("{}/open-apis/bot/v2/hook/xyz", server.url),
+            secret: Some("feishu-secret".to_string()),
+        };
+        notifier.send(&report()).await.unwrap();
+
+        let body = body(&server);
+        assert_eq!(body["msg_type"], "text");
+        assert!(body["content"]["text"].as_str().unwrap().contains("Archive: nginx_1.27.tar.gz"));
+        let timestamp = body["timestamp"].as_str().unwrap();
+        assert_eq!(timestamp.len(), 10, "seconds, not milliseconds");
+        let expected = sign(format!("{}\nfeishu-secret", timestamp).as_bytes(), b"");
+        assert_eq!(body["sign"], expected);
+    }
+
+    #[tokio::test]
+    async fn posts_each_notifier_body() {
+        let server = TestServer::start(|_| Response::ok("ok")).await;
+
+        Notifier::DingTalk { url: server.url.clone(), secret: None }
+            .send(&report())
+            .await
+            .unwrap();
+        Notifier::WeCom { url: server.url.clone() }.send(&report()).await.unwrap();
+        Notifier::Slack { url: server.url.clone() }.send(&report()).await.unwrap();
+        Notifier::Webhook { url: server.url.clone() }.send(&report()).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        let bodies: Vec<Value> = requests
+            .iter()
+            .map(|r| serde_json::from_slice(&r.body).unwrap())
+            .collect();
+
+        assert_eq!(requests[0].query("sign"), None);
+        assert_eq!(bodies[0]["msgtype"], "markdown");
+        assert_eq!(bodies[1]["msgtype"], "markdown");
+        assert!(bodies[1]["markdown"]["content"].as_str().unwrap().starts_with("### ✅"));
+        assert!(bodies[2]["text"].as_str().unwrap().starts_with("*✅ Image transfer succeeded"));
+        assert_eq!(bodies[3]["status"], "success");
+        assert_eq!(bodies[3]["organization"], "library");
+        assert_eq!(bodies[3]["name"], "nginx");
+        assert_eq!(bodies[3]["tag"], "1.27");
+        assert_eq!(bodies[3]["duration_secs"], 65);
+    }
+
+    #[tokio::test]
+    async fn error_code_in_200_response_fails() {
+        let server = TestServer::start(|request| match request.path() {
+            "/dingtalk" => Response::ok(r#"{"errcode":310000,"errmsg":"sign not match"}"#),
+            "/feishu" => Response::ok(r#"{"code":19021,"msg":"sign match fail"}"#),
+            _ => Response::status(500, "boom"),
+        })
+        .await;
+
+        let error = Notifier::DingTalk { url: format!("{}/dingtalk", server.url), secret: None }
+            .send(&report())
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "DingTalk notification failed: 310000 sign not match");
+
+        let error = Notifier::Feishu { url: format!("{}/feishu", server.url), secret: None }
+            .send(&report())
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Feishu notification failed: 19021 sign match fail");
+
+        let error = Notifier::Slack { url: format!("{}/slack", server.url) }
+            .send(&report())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Slack notification failed: 500"));
+    }
+}
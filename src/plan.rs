@@ -0,0 +1,295 @@
+use crate::cache::BlobCache;
+use crate::cas::{self, BlobStore};
+use crate::destination::Destination;
+use crate::download::resolve_manifest;
+use crate::inspect::format_size;
+use crate::registry::{parse_image_ref, RegistryClient};
+use crate::types::{Descriptor, ManifestListResponse, ManifestResponse};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// tar 块大小
+const TAR_BLOCK: u64 = 512;
+
+/// Blob 在传输计划中的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobStatus {
+    /// 需要从源 registry 下载
+    Download,
+    /// 已在本地缓存中
+    Cached,
+    /// 已在远程 Blob 存储中，从存储而不是源 registry 获取
+    InBlobStore,
+    /// 目标端已存在，无需传输
+    AtDestination,
+}
+
+/// 计划中的单个 Blob
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedBlob {
+    pub digest: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    pub status: BlobStatus,
+}
+
+/// 传输计划（`--dry-run` 输出），不写入任何 Blob
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferPlan {
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    /// 引用解析出的 Manifest digest
+    pub digest: String,
+    /// 选中平台的 Manifest digest（保留多架构时与 `digest` 相同）
+    pub platform_digest: String,
+    pub blobs: Vec<PlannedBlob>,
+    /// 所有 Blob 的压缩后大小
+    pub total_bytes: u64,
+    /// 需要下载的字节数
+    pub download_bytes: u64,
+    /// 缓存命中的字节数
+    pub cached_bytes: u64,
+    /// 从远程 Blob 存储获取的字节数
+    pub blob_store_bytes: u64,
+    /// 目标端已存在的字节数
+    pub existing_bytes: u64,
+    /// 预计归档大小（只对下载模式有意义）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_archive_bytes: Option<u64>,
+}
+
+impl TransferPlan {
+    fn new(
+        source: &str,
+        destination: Option<&str>,
+        digest: String,
+        platform_digest: String,
+        blobs: Vec<PlannedBlob>,
+    ) -> Self {
+        let sum = |status: BlobStatus| {
+            blobs
+                .iter()
+                .filter(|b| b.status == status)
+                .map(|b| b.size)
+                .sum::<u64>()
+        };
+
+        Self {
+            source: source.to_string(),
+            destination: destination.map(str::to_string),
+            digest,
+            platform_digest,
+            total_bytes: blobs.iter().map(|b| b.size).sum(),
+            download_bytes: sum(BlobStatus::Download),
+            cached_bytes: sum(BlobStatus::Cached),
+            blob_store_bytes: sum(BlobStatus::InBlobStore),
+            existing_bytes: sum(BlobStatus::AtDestination),
+            estimated_archive_bytes: None,
+            blobs,
+        }
+    }
+}
+
+/// 生成下载模式的传输计划
+///
+/// `destinations` 为内容寻址模式（`REMOTE_CAS`）的上传目标，所有目标上都已有的 Blob
+/// 无需传输；其余 Blob 依次查找本地缓存与远程 Blob 存储，与实际下载的顺序一致。
+pub async fn plan_download(
+    image_ref: &str,
+    username: Option<String>,
+    password: Option<String>,
+    cache: Option<&BlobCache>,
+    blob_store: Option<&BlobStore>,
+    destinations: &[Arc<dyn Destination>],
+) -> Result<TransferPlan> {
+    let image = parse_image_ref(image_ref)?;
+
+    let scope = format!("repository:{}:pull", image.repository);
+    let mut client = RegistryClient::new(image.registry.clone(), username, password)?;
+
+    eprintln!("Authenticating...");
+    client.authenticate(&image.repository, &scope).await?;
+
+    let resolved = resolve_manifest(&client, &image.repository, &image.reference).await?;
+    let manifest = &resolved.manifest;
+
+    let mut blobs = Vec::new();
+    for descriptor in image_blobs(manifest) {
+        let status = download_status(descriptor, cache, blob_store, destinations).await?;
+        blobs.push(planned(descriptor, status));
+    }
+
+    let locations: Vec<String> = destinations.iter().map(|d| d.location()).collect();
+    let mut plan = TransferPlan::new(
+        image_ref,
+        (!locations.is_empty()).then(|| locations.join(", ")).as_deref(),
+        resolved.digest.clone(),
+        resolved.platform_digest.clone(),
+        blobs,
+    );
+    // 归档中的 manifest.json 是 registry 返回的原始字节
+    let manifest_size = resolved.manifest_bytes.len() as u64;
+    plan.estimated_archive_bytes = Some(estimate_archive_size(&plan.blobs, manifest_size));
+
+    Ok(plan)
+}
+
+/// 生成 registry 之间复制的传输计划，检查目标端已有的 Blob
+pub async fn plan_copy(
+    src_ref: &str,
+    dst_ref: &str,
+    src_credentials: (Option<String>, Option<String>),
+    dst_credentials: (Option<String>, Option<String>),
+    all_platforms: bool,
+) -> Result<TransferPlan> {
+    let src_image = parse_image_ref(src_ref)?;
+    let dst_image = parse_image_ref(dst_ref)?;
+
+    let (src_username, src_password) = src_credentials;
+    let mut src = RegistryClient::new(src_image.registry.clone(), src_username, src_password)?;
+    let src_scope = format!("repository:{}:pull", src_image.repository);
+    eprintln!("Authenticating to source...");
+    src.authenticate(&src_image.repository, &src_scope).await?;
+
+    // dry-run 只需要 pull 权限来检查目标端
+    let (dst_username, dst_password) = dst_credentials;
+    let mut dst = RegistryClient::new(dst_image.registry.clone(), dst_username, dst_password)?;
+    let dst_scope = format!("repository:{}:pull", dst_image.repository);
+    eprintln!("Authenticating to target...");
+    dst.authenticate(&dst_image.repository, &dst_scope).await?;
+
+    let resolved = resolve_manifest(&src, &src_image.repository, &src_image.reference).await?;
+
+    // 保留多架构时需要计划每个平台的 Blob
+    let mut manifests = Vec::new();
+    let platform_digest = match (&resolved.index, all_platforms) {
+        (Some(index), true) => {
+            let index: ManifestListResponse = serde_json::from_value(index.clone())?;
+            for entry in index.manifests {
+                let (_, child) = src.fetch_manifest(&src_image.repository, &entry.digest).await?;
+                manifests.push(serde_json::from_value::<ManifestResponse>(child)?);
+            }
+            resolved.digest.clone()
+        }
+        _ => {
+            manifests.push(resolved.manifest.clone());
+            resolved.platform_digest.clone()
+        }
+    };
+
+    let mut seen = HashSet::new();
+    let mut blobs = Vec::new();
+    for manifest in &manifests {
+        for descriptor in image_blobs(manifest) {
+            if !seen.insert(descriptor.digest.clone()) {
+                continue;
+            }
+
+            let exists = dst.blob_exists(&dst_image.repository, &descriptor.digest).await?;
+            blobs.push(planned(descriptor, if exists { BlobStatus::AtDestination } else { BlobStatus::Download }));
+        }
+    }
+
+    Ok(TransferPlan::new(src_ref, Some(dst_ref), resolved.digest.clone(), platform_digest, blobs))
+}
+
+/// 输出便于阅读的计划摘要
+pub fn print_summary(plan: &TransferPlan) {
+    eprintln!("========================================");
+    eprintln!("Transfer plan (dry run)");
+    eprintln!("========================================");
+    eprintln!("Source: {}", plan.source);
+    if let Some(destination) = &plan.destination {
+        eprintln!("Target: {}", destination);
+    }
+    eprintln!("Digest: {}", plan.digest);
+    for blob in &plan.blobs {
+        let status = match blob.status {
+            BlobStatus::Download => "download",
+            BlobStatus::Cached => "cached",
+            BlobStatus::InBlobStore => "store",
+            BlobStatus::AtDestination => "exists",
+        };
+        eprintln!("  {:<8}  {:>10}  {}", status, format_size(blob.size), blob.digest);
+    }
+    eprintln!("Total:      {}", format_size(plan.total_bytes));
+    eprintln!("Download:   {}", format_size(plan.download_bytes));
+    eprintln!("Cached:     {}", format_size(plan.cached_bytes));
+    if plan.blob_store_bytes > 0 {
+        eprintln!("Blob store: {}", format_size(plan.blob_store_bytes));
+    }
+    if plan.destination.is_some() {
+        eprintln!("At target:  {}", format_size(plan.existing_bytes));
+    }
+    if let Some(archive_bytes) = plan.estimated_archive_bytes {
+        eprintln!("Archive:    ~{}", format_size(archive_bytes));
+    }
+}
+
+/// 下载模式下 Blob 的状态：所有目标都已有、本地缓存命中、Blob 存储命中，否则从 registry 下载
+async fn download_status(
+    descriptor: &Descriptor,
+    cache: Option<&BlobCache>,
+    blob_store: Option<&BlobStore>,
+    destinations: &[Arc<dyn Destination>],
+) -> Result<BlobStatus> {
+    let mut at_destinations = !destinations.is_empty();
+    for destination in destinations {
+        if !cas::has_blob(destination.as_ref(), &descriptor.digest, descriptor.size).await? {
+            at_destinations = false;
+            break;
+        }
+    }
+    if at_destinations {
+        return Ok(BlobStatus::AtDestination);
+    }
+
+    if let Some(cache) = cache {
+        if cache.contains(&descriptor.digest, descriptor.size).await {
+            return Ok(BlobStatus::Cached);
+        }
+    }
+    if let Some(store) = blob_store {
+        if store.contains(&descriptor.digest, descriptor.size).await {
+            return Ok(BlobStatus::InBlobStore);
+        }
+    }
+
+    Ok(BlobStatus::Download)
+}
+
+/// config 与 layers（config 在前）
+fn image_blobs(manifest: &ManifestResponse) -> Vec<&Descriptor> {
+    let mut descriptors = vec![&manifest.config];
+    descriptors.extend(manifest.layers.iter());
+    descriptors
+}
+
+fn planned(descriptor: &Descriptor, status: BlobStatus) -> PlannedBlob {
+    PlannedBlob {
+        digest: descriptor.digest.clone(),
+        size: descriptor.size,
+        media_type: descriptor.media_type.clone(),
+        status,
+    }
+}
+
+/// 估算归档大小：每个文件一个 tar 头并补齐到 512 字节，末尾两个空块。
+/// layers 已经压缩，外层 gzip 基本不会再改变大小。
+fn estimate_archive_size(blobs: &[PlannedBlob], manifest_size: u64) -> u64 {
+    let entry = |size: u64| TAR_BLOCK + size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+
+    let mut seen = HashSet::new();
+    let blobs_size: u64 = blobs
+        .iter()
+        .filter(|b| seen.insert(b.digest.as_str()))
+        .map(|b| entry(b.size))
+        .sum();
+
+    blobs_size + entry(manifest_size) + 2 * TAR_BLOCK
+}
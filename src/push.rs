@@ -0,0 +1,217 @@
+use crate::registry::{parse_image_ref, RegistryClient};
+use crate::tar;
+use crate::types::{ManifestResponse, MEDIA_TYPE_DOCKER_MANIFEST, MEDIA_TYPE_OCI_MANIFEST};
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use tokio::fs;
+
+/// 超过该大小的 Blob 使用分块上传
+const CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// 将下载目录或 tar.gz 归档推送到目标 registry
+///
+/// `source` 可以是 `download_image` 的输出目录（含 `manifest.json` 与 `blobs/`），
+/// 也可以是 `create_tar_archive` 生成的归档文件。
+pub async fn push_image(
+    source: &Path,
+    target_ref: &str,
+    username: Option<String>,
+    password: Option<String>,
+    mount_from: Option<&str>,
+) -> Result<String> {
+    if source.is_file() {
+        // 归档先解包到临时目录
+        let staging_dir = std::env::temp_dir().join(format!(
+            "push-{}",
+            tar::sanitize_filename(target_ref)
+        ));
+        if staging_dir.exists() {
+            fs::remove_dir_all(&staging_dir).await?;
+        }
+        tar::extract_tar_archive(source, &staging_dir)?;
+
+        let result = push_dir(&staging_dir, target_ref, username, password, mount_from).await;
+        fs::remove_dir_all(&staging_dir).await?;
+        return result;
+    }
+
+    push_dir(source, target_ref, username, password, mount_from).await
+}
+
+/// 推送下载目录中的镜像
+async fn push_dir(
+    source_dir: &Path,
+    target_ref: &str,
+    username: Option<String>,
+    password: Option<String>,
+    mount_from: Option<&str>,
+) -> Result<String> {
+    let image = parse_image_ref(target_ref)?;
+
+    eprintln!("Registry: {}", image.registry);
+    eprintln!("Repository: {}", image.repository);
+    eprintln!("Reference: {}", image.reference);
+
+    // 1. 读取 manifest
+    let manifest_path = source_dir.join("manifest.json");
+    let manifest_bytes = fs::read(&manifest_path)
+        .await
+        .map_err(|e| anyhow!("Failed to read {}: {}", manifest_path.display(), e))?;
+    let manifest: ManifestResponse = serde_json::from_slice(&manifest_bytes)?;
+
+    // 2. 认证（需要 push 权限，跨仓库挂载还需要源仓库的 pull 权限）
+    let mut scope = format!("repository:{}:pull,push", image.repository);
+    if let Some(from) = mount_from {
+        scope.push_str(&format!(" repository:{}:pull", from));
+    }
+    let mut client = RegistryClient::new(image.registry.clone(), username, password)?;
+
+    eprintln!("Authenticating...");
+    client.authenticate(&image.repository, &scope).await?;
+
+    // 3. 上传 Config 与所有 Layers
+    let blobs_dir = source_dir.join("blobs");
+    let mut descriptors = vec![&manifest.config];
+    descriptors.extend(manifest.layers.iter());
+
+    for (idx, blob) in descriptors.iter().enumerate() {
+        eprintln!(
+            "Blob {}/{}: {} ({} bytes)",
+            idx + 1,
+            descriptors.len(),
+            blob.digest,
+            blob.size
+        );
+
+        if client.blob_exists(&image.repository, &blob.digest).await? {
+            eprintln!("Blob already exists, skipping");
+            continue;
+        }
+
+        if let Some(from) = mount_from {
+            if client.mount_blob(&image.repository, &blob.digest, from).await? {
+                eprintln!("Blob mounted from {}", from);
+                continue;
+            }
+        }
+
+        let blob_path = blobs_dir.join(&blob.digest);
+        let size = fs::metadata(&blob_path)
+            .await
+            .map_err(|e| anyhow!("Missing blob {}: {}", blob_path.display(), e))?
+            .len();
+
+        if size as usize <= CHUNK_SIZE {
+            let data = fs::read(&blob_path).await?;
+            client
+                .upload_blob_monolithic(&image.repository, &blob.digest, data)
+                .await?;
+        } else {
+            client
+                .upload_blob_chunked(&image.repository, &blob.digest, &blob_path, CHUNK_SIZE)
+                .await?;
+        }
+    }
+
+    // 4. 上传 Manifest
+    let media_type = match &manifest.media_type {
+        Some(media_type) => media_type.as_str(),
+        None if manifest
+            .config
+            .media_type
+            .as_deref()
+            .is_some_and(|t| t.contains("oci")) =>
+        {
+            MEDIA_TYPE_OCI_MANIFEST
+        }
+        None => MEDIA_TYPE_DOCKER_MANIFEST,
+    };
+
+    eprintln!("Pushing manifest ({})...", media_type);
+    let digest = client
+        .put_manifest(&image.repository, &image.reference, media_type, manifest_bytes)
+        .await?;
+
+    eprintln!("Manifest pushed: {}", digest);
+
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest;
+    use crate::test_server::{Response, TestServer};
+
+    #[tokio::test]
+    async fn pushes_missing_blobs_and_manifest_with_its_media_type() {
+        let config = b"{}".to_vec();
+        let layer = b"layer data".to_vec();
+        let config_digest = digest::sha256_digest(&config);
+        let layer_digest = digest::sha256_digest(&layer);
+
+        let existing = format!("/v2/app/blobs/{}", config_digest);
+        let server = TestServer::start(move |request| match (request.method.as_str(), request.path()) {
+            ("GET", "/v2/") => Response::ok(""),
+            ("HEAD", path) if path == existing => Response::ok(""),
+            ("HEAD", _) => Response::status(404, ""),
+            // 挂载未执行，退化为上传会话
+            ("POST", _) if request.query("mount").is_some() => {
+                Response::status(202, "").with_header("Location", "/v2/app/blobs/uploads/unused")
+            }
+            ("DELETE", _) => Response::status(204, ""),
+            ("POST", _) => Response::status(202, "").with_header("Location", "/v2/app/blobs/uploads/session"),
+            ("PUT", "/v2/app/blobs/uploads/session") => Response::status(201, ""),
+            ("PUT", "/v2/app/manifests/v1") => {
+                Response::status(201, "").with_header("Docker-Content-Digest", "sha256:pushed")
+            }
+            _ => Response::status(400, ""),
+        })
+        .await;
+
+        // 没有 mediaType 字段时按 config 的媒体类型推断
+        let source_dir = std::env::temp_dir().join(format!("push-test-{}", std::process::id()));
+        std::fs::create_dir_all(source_dir.join("blobs")).unwrap();
+        std::fs::write(source_dir.join("blobs").join(&config_digest), &config).unwrap();
+        std::fs::write(source_dir.join("blobs").join(&layer_digest), &layer).unwrap();
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "size": config.len(),
+                "digest": config_digest,
+            },
+            "layers": [{
+                "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                "size": layer.len(),
+                "digest": layer_digest,
+            }],
+        });
+        let manifest_bytes = serde_json::to_vec(&manifest).unwrap();
+        std::fs::write(source_dir.join("manifest.json"), &manifest_bytes).unwrap();
+
+        let target = format!("{}/app:v1", server.url.trim_start_matches("http://"));
+        let digest = push_image(&source_dir, &target, None, None, Some("base")).await.unwrap();
+        std::fs::remove_dir_all(&source_dir).unwrap();
+        assert_eq!(digest, "sha256:pushed");
+
+        let requests = server.requests();
+        let calls: Vec<_> = requests.iter().map(|r| format!("{} {}", r.method, r.target)).collect();
+        assert_eq!(
+            calls,
+            [
+                "GET /v2/".to_string(),
+                format!("HEAD /v2/app/blobs/{}", config_digest),
+                format!("HEAD /v2/app/blobs/{}", layer_digest),
+                format!("POST /v2/app/blobs/uploads/?mount={}&from=base", layer_digest),
+                "DELETE /v2/app/blobs/uploads/unused".to_string(),
+                "POST /v2/app/blobs/uploads/".to_string(),
+                format!("PUT /v2/app/blobs/uploads/session?digest={}", layer_digest),
+                "PUT /v2/app/manifests/v1".to_string(),
+            ]
+        );
+        assert_eq!(requests[6].body, layer);
+        assert_eq!(requests[7].header("content-type"), Some(MEDIA_TYPE_OCI_MANIFEST));
+        assert_eq!(requests[7].body, manifest_bytes);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::digest;
+use crate::registry::RegistryClient;
+use crate::types::{Descriptor, ManifestResponse, ReferrersResponse};
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use tokio::fs;
+
+/// BuildKit 在 Index 中标记 attestation manifest 的注解
+pub const REFERENCE_TYPE_ANNOTATION: &str = "vnd.docker.reference.type";
+/// BuildKit attestation manifest 指向的镜像 digest
+const REFERENCE_DIGEST_ANNOTATION: &str = "vnd.docker.reference.digest";
+/// BuildKit attestation 没有 artifactType，按其 layer 的 in-toto 媒体类型归类
+const BUILDKIT_ATTESTATION_TYPE: &str = "application/vnd.in-toto+json";
+
+/// 查询引用 `subject_digest` 的制品（SBOM、签名、provenance 等）
+///
+/// 优先使用 `/v2/<name>/referrers/<digest>`，registry 不支持时回退到
+/// `sha256-<hex>` tag 方案。
+pub async fn discover(
+    client: &RegistryClient,
+    repository: &str,
+    subject_digest: &str,
+) -> Result<Vec<Descriptor>> {
+    if let Some(referrers) = client.fetch_referrers(repository, subject_digest).await? {
+        return Ok(referrers.manifests);
+    }
+
+    eprintln!("Referrers API not supported, falling back to tag schema...");
+    let tag = subject_digest.replace(':', "-");
+    match client.fetch_manifest_if_exists(repository, &tag).await? {
+        Some((_, body)) => {
+            let referrers: ReferrersResponse = serde_json::from_slice(&body)?;
+            Ok(referrers.manifests)
+        }
+        // 没有 fallback tag 说明没有任何 referrer
+        None => Ok(Vec::new()),
+    }
+}
+
+/// 从 BuildKit 生成的 Index 中找出指向 `subject_digest` 的 attestation manifest
+pub fn buildkit_attestations(
+    manifest_list: &serde_json::Value,
+    subject_digest: &str,
+) -> Result<Vec<Descriptor>> {
+    let manifests = match manifest_list.get("manifests").and_then(|v| v.as_array()) {
+        Some(manifests) => manifests,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut attestations = Vec::new();
+    for manifest in manifests {
+        let annotations = manifest.get("annotations");
+        let reference_type = annotations
+            .and_then(|a| a.get(REFERENCE_TYPE_ANNOTATION))
+            .and_then(|v| v.as_str());
+        let reference_digest = annotations
+            .and_then(|a| a.get(REFERENCE_DIGEST_ANNOTATION))
+            .and_then(|v| v.as_str());
+
+        if reference_type == Some("attestation-manifest") && reference_digest == Some(subject_digest) {
+            let mut descriptor: Descriptor = serde_json::from_value(manifest.clone())?;
+            descriptor.artifact_type = Some(BUILDKIT_ATTESTATION_TYPE.to_string());
+            attestations.push(descriptor);
+        }
+    }
+
+    Ok(attestations)
+}
+
+/// 判断制品是否匹配请求的 artifactType 列表（`*` 匹配全部）
+pub fn matches(descriptor: &Descriptor, artifact_types: &[String]) -> bool {
+    artifact_types.iter().any(|t| {
+        t == "*" || descriptor.artifact_type.as_deref() == Some(t.as_str())
+    })
+}
+
+/// 下载制品的 Manifest 与 Blob，保存到 `output_dir/referrers/<digest>/`
+///
+/// Manifest 与每个 Blob 都按 digest 校验，内容被篡改时报错。
+pub async fn download_referrers(
+    client: &RegistryClient,
+    repository: &str,
+    referrers: &[Descriptor],
+    output_dir: &Path,
+) -> Result<Vec<String>> {
+    let referrers_dir = output_dir.join("referrers");
+    fs::create_dir_all(&referrers_dir).await?;
+
+    // 保存制品列表，方便接收方按 artifactType 查找
+    let index_path = referrers_dir.join("index.json");
+    fs::write(&index_path, serde_json::to_string_pretty(referrers)?).await?;
+
+    let mut downloaded_files = vec![index_path.to_string_lossy().to_string()];
+
+    for (idx, referrer) in referrers.iter().enumerate() {
+        eprintln!(
+            "Referrer {}/{}: {} ({})",
+            idx + 1,
+            referrers.len(),
+            referrer.digest,
+            referrer.artifact_type.as_deref().unwrap_or("unknown")
+        );
+
+        let artifact_dir = referrers_dir.join(&referrer.digest);
+        let blobs_dir = artifact_dir.join("blobs");
+        fs::create_dir_all(&blobs_dir).await?;
+
+        let (_, manifest_bytes) = client.fetch_manifest_raw(repository, &referrer.digest).await?;
+        let manifest_digest = digest::sha256_digest(&manifest_bytes);
+        if manifest_digest != referrer.digest {
+            return Err(anyhow!(
+                "Referrer manifest digest mismatch: expected {}, got {}",
+                referrer.digest,
+                manifest_digest
+            ));
+        }
+        let manifest: ManifestResponse = serde_json::from_slice(&manifest_bytes)?;
+        let manifest_path = artifact_dir.join("manifest.json");
+        fs::write(&manifest_path, &manifest_bytes).await?;
+        downloaded_files.push(manifest_path.to_string_lossy().to_string());
+
+        let mut descriptors = vec![&manifest.config];
+        descriptors.extend(manifest.layers.iter());
+        for blob in descriptors {
+            let blob_path = blobs_dir.join(&blob.digest);
+            client.download_blob(repository, &blob.digest, &blob_path).await?;
+            if let Err(e) = digest::verify_file(&blob_path, &blob.digest) {
+                fs::remove_file(&blob_path).await?;
+                return Err(e);
+            }
+            downloaded_files.push(blob_path.to_string_lossy().to_string());
+        }
+    }
+
+    Ok(downloaded_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    /// 提供一个 SBOM 制品的 registry 替身，`blob_body` 为 layer 实际返回的内容
+    async fn sbom_registry(manifest_body: Option<Vec<u8>>, blob_body: &[u8]) -> (TestServer, Descriptor) {
+        let sbom = br#"{"spdxVersion":"SPDX-2.3"}"#.to_vec();
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "artifactType": "application/spdx+json",
+            "config": {
+                "mediaType": "application/vnd.oci.empty.v1+json",
+                "size": 2,
+                "digest": digest::sha256_digest(b"{}"),
+            },
+            "layers": [{
+                "mediaType": "application/spdx+json",
+                "size": sbom.len(),
+                "digest": digest::sha256_digest(&sbom),
+            }],
+        });
+        let manifest_bytes = serde_json::to_vec(&manifest).unwrap();
+        let descriptor: Descriptor = serde_json::from_value(serde_json::json!({
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "size": manifest_bytes.len(),
+            "digest": digest::sha256_digest(&manifest_bytes),
+            "artifactType": "application/spdx+json",
+        }))
+        .unwrap();
+
+        let mut routes = HashMap::new();
+        routes.insert(
+            format!("/v2/app/manifests/{}", descriptor.digest),
+            manifest_body.unwrap_or(manifest_bytes),
+        );
+        routes.insert(format!("/v2/app/blobs/{}", digest::sha256_digest(b"{}")), b"{}".to_vec());
+        routes.insert(format!("/v2/app/blobs/{}", digest::sha256_digest(&sbom)), blob_body.to_vec());
+        let routes = Arc::new(routes);
+
+        let server = TestServer::start(move |request| match routes.get(request.path()) {
+            Some(body) => Response::ok(body.clone()),
+            None => Response::status(404, ""),
+        })
+        .await;
+        (server, descriptor)
+    }
+
+    async fn download(server: &TestServer, descriptor: &Descriptor, name: &str) -> (Result<Vec<String>>, std::path::PathBuf) {
+        let registry = server.url.trim_start_matches("http://").to_string();
+        let client = RegistryClient::new(registry, None, None).unwrap();
+        let output_dir = std::env::temp_dir().join(format!("referrers-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&output_dir);
+        let result = download_referrers(&client, "app", std::slice::from_ref(descriptor), &output_dir).await;
+        (result, output_dir)
+    }
+
+    #[tokio::test]
+    async fn downloads_verified_referrer() {
+        let (server, descriptor) = sbom_registry(None, br#"{"spdxVersion":"SPDX-2.3"}"#).await;
+        let (result, output_dir) = download(&server, &descriptor, "ok").await;
+
+        assert_eq!(result.unwrap().len(), 4);
+        let artifact_dir = output_dir.join("referrers").join(&descriptor.digest);
+        let manifest = std::fs::read(artifact_dir.join("manifest.json")).unwrap();
+        assert_eq!(digest::sha256_digest(&manifest), descriptor.digest);
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_manifest() {
+        let (server, descriptor) = sbom_registry(Some(b"{\"layers\":[]}".to_vec()), b"").await;
+        let (result, output_dir) = download(&server, &descriptor, "manifest").await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("Referrer manifest digest mismatch"), "{}", error);
+        assert!(!output_dir.join("referrers").join(&descriptor.digest).join("manifest.json").exists());
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_blob() {
+        let (server, descriptor) = sbom_registry(None, br#"{"spdxVersion":"forged"}"#).await;
+        let (result, output_dir) = download(&server, &descriptor, "blob").await;
+
+        assert!(result.is_err());
+        let layer_digest = digest::sha256_digest(br#"{"spdxVersion":"SPDX-2.3"}"#);
+        let blob_path = output_dir.join("referrers").join(&descriptor.digest).join("blobs").join(layer_digest);
+        assert!(!blob_path.exists());
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
@@ -1,9 +1,10 @@
 use crate::types::*;
 use anyhow::{anyhow, Result};
 use base64::Engine;
-use reqwest::{header, Client, StatusCode};
+use reqwest::{header, Body, Client, RequestBuilder, Response, StatusCode};
+use std::path::Path;
 use std::time::Duration;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::fs::File;
 use futures_util::stream::StreamExt;
 
@@ -11,6 +12,7 @@ use futures_util::stream::StreamExt;
 pub struct RegistryClient {
     client: Client,
     registry: String,
+    scheme: &'static str,
     username: Option<String>,
     password: Option<String>,
     token: Option<String>,
@@ -27,9 +29,12 @@ impl RegistryClient {
             .timeout(Duration::from_secs(1800)) // 30分钟超时
             .build()?;
 
+        let scheme = registry_scheme(&registry);
+
         Ok(Self {
             client,
             registry,
+            scheme,
             username,
             password,
             token: None,
@@ -37,9 +42,12 @@ impl RegistryClient {
     }
 
     /// 获取认证 Token
-    pub async fn authenticate(&mut self, repository: &str, scope: &str) -> Result<String> {
+    ///
+    /// `scope` 可以包含多个以空格分隔的范围（如跨仓库挂载时的
+    /// `repository:<target>:pull,push repository:<source>:pull`）。
+    pub async fn authenticate(&mut self, _repository: &str, scope: &str) -> Result<String> {
         // 触发认证挑战
-        let url = format!("https://{}/v2/", self.registry);
+        let url = format!("{}/v2/", self.base_url());
         let resp = self.client.get(&url).send().await?;
 
         if resp.status() != StatusCode::UNAUTHORIZED {
@@ -59,50 +67,33 @@ impl RegistryClient {
         // 构建请求 URL
         let mut token_url = format!("{}?service={}", auth_config.realm, auth_config.service.unwrap_or_default());
 
-        // 使用传入的 scope 参数
-        if !scope.is_empty() {
+        // 每个 scope 作为单独的查询参数
+        for scope in scope.split_whitespace() {
             token_url.push_str(&format!("&scope={}", scope));
         }
 
-        // 智能认证策略：
-        // 1. 对于公开镜像，先尝试匿名获取 token（不发送认证信息）
-        // 2. 只有在匿名请求失败时才使用提供的凭证
+        // 认证策略：
+        // 1. 提供了凭证时始终携带 Basic Auth（匿名 token 不含 push 权限，私有仓库也无法拉取）
+        // 2. 没有凭证时匿名获取 token（公开镜像）
         // 3. Docker Hub Access Token 的正确用法：空用户名 + token 作为密码
-
-        // 先尝试匿名请求（用于公开镜像）
-        let anon_resp = self.client.get(&token_url).send().await?;
-
-        if anon_resp.status().is_success() {
-            // 匿名访问成功，直接返回 token
-            let token_resp: TokenResponse = anon_resp.json().await?;
-            let token = token_resp.access_token.unwrap_or(token_resp.token);
-            self.token = Some(token.clone());
-            return Ok(token);
-        }
-
-        // 匿名请求失败，尝试使用凭证（如果提供了）
-        if self.password.is_none() {
-            return Err(anyhow!("Anonymous access failed and no credentials provided"));
-        }
-
         let mut req = self.client.get(&token_url);
 
-        // 添加 Basic Auth
-        let password = self.password.as_ref().unwrap();
-        let credentials = if let Some(username) = &self.username {
-            // 如果用户名不为空，使用 username:password 格式
-            // 如果密码看起来像 Access Token（很长），忽略用户名
-            if password.len() > 50 {
-                format!(":{}", password)  // 空用户名 + token 作为密码
+        if let Some(password) = &self.password {
+            let credentials = if let Some(username) = &self.username {
+                // 如果用户名不为空，使用 username:password 格式
+                // 如果密码看起来像 Access Token（很长），忽略用户名
+                if password.len() > 50 {
+                    format!(":{}", password)  // 空用户名 + token 作为密码
+                } else {
+                    format!("{}:{}", username, password)  // 用户名 + 密码
+                }
             } else {
-                format!("{}:{}", username, password)  // 用户名 + 密码
-            }
-        } else {
-            // 没有用户名，只有密码（应该是 token）
-            format!(":{}", password)
-        };
-        let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
-        req = req.header(header::AUTHORIZATION, format!("Basic {}", encoded));
+                // 没有用户名，只有密码（应该是 token）
+                format!(":{}", password)
+            };
+            let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+            req = req.header(header::AUTHORIZATION, format!("Basic {}", encoded));
+        }
 
         let resp = req.send().await?;
 
@@ -126,9 +117,32 @@ impl RegistryClient {
         repository: &str,
         reference: &str,
     ) -> Result<(String, serde_json::Value)> {
+        let (content_type, body) = self.fetch_manifest_raw(repository, reference).await?;
+        let json: serde_json::Value = serde_json::from_slice(&body)?;
+
+        Ok((content_type, json))
+    }
+
+    /// 获取 Manifest 原始字节（保持 digest 不变）
+    pub async fn fetch_manifest_raw(
+        &self,
+        repository: &str,
+        reference: &str,
+    ) -> Result<(String, Vec<u8>)> {
+        self.fetch_manifest_if_exists(repository, reference)
+            .await?
+            .ok_or_else(|| anyhow!("Failed to fetch manifest: {} not found", reference))
+    }
+
+    /// 获取 Manifest 原始字节，Manifest 不存在（404）时返回 `None`，其他错误照常返回
+    pub async fn fetch_manifest_if_exists(
+        &self,
+        repository: &str,
+        reference: &str,
+    ) -> Result<Option<(String, Vec<u8>)>> {
         let url = format!(
-            "https://{}/v2/{}/manifests/{}",
-            self.registry, repository, reference
+            "{}/v2/{}/manifests/{}",
+            self.base_url(), repository, reference
         );
 
         let accept = "application/vnd.docker.distribution.manifest.v2+json, \
@@ -144,6 +158,9 @@ impl RegistryClient {
 
         let resp = req.send().await?;
 
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
         if !resp.status().is_success() {
             let status = resp.status();
             let error_text = resp.text().await.unwrap_or_default();
@@ -157,10 +174,9 @@ impl RegistryClient {
             .unwrap_or("")
             .to_string();
 
-        let body = resp.text().await?;
-        let json: serde_json::Value = serde_json::from_str(&body)?;
+        let body = resp.bytes().await?.to_vec();
 
-        Ok((content_type, json))
+        Ok(Some((content_type, body)))
     }
 
     /// 下载 Blob（支持断点续传）
@@ -171,8 +187,8 @@ impl RegistryClient {
         output_path: &std::path::Path,
     ) -> Result<()> {
         let url = format!(
-            "https://{}/v2/{}/blobs/{}",
-            self.registry, repository, digest
+            "{}/v2/{}/blobs/{}",
+            self.base_url(), repository, digest
         );
 
         // 检查是否有部分下载的文件
@@ -238,6 +254,326 @@ impl RegistryClient {
 
         Ok(())
     }
+
+    /// 通过 OCI Referrers API 查询引用指定 Manifest 的制品
+    ///
+    /// registry 不支持 Referrers API（404，部分实现返回 400 / 405）时返回 `None`，调用方应回退到 tag 方案。
+    pub async fn fetch_referrers(
+        &self,
+        repository: &str,
+        digest: &str,
+    ) -> Result<Option<ReferrersResponse>> {
+        let url = format!("{}/v2/{}/referrers/{}", self.base_url(), repository, digest);
+        let resp = self
+            .authorize(self.client.get(&url))
+            .header(header::ACCEPT, "application/vnd.oci.image.index.v1+json")
+            .send()
+            .await?;
+
+        match resp.status() {
+            StatusCode::OK => Ok(Some(resp.json().await?)),
+            StatusCode::NOT_FOUND | StatusCode::BAD_REQUEST | StatusCode::METHOD_NOT_ALLOWED => Ok(None),
+            status => {
+                let error_text = resp.text().await.unwrap_or_default();
+                Err(anyhow!("Failed to fetch referrers: {} - {}", status, error_text))
+            }
+        }
+    }
+
+    /// 获取 Blob 响应，由调用方以流的方式读取内容
+    pub async fn fetch_blob(&self, repository: &str, digest: &str) -> Result<Response> {
+        let url = format!("{}/v2/{}/blobs/{}", self.base_url(), repository, digest);
+        let resp = self
+            .authorize(self.client.get(&url))
+            .header(header::ACCEPT_ENCODING, "identity")
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(anyhow!("Failed to download blob {}: {}", digest, resp.status()));
+        }
+
+        Ok(resp)
+    }
+
+    /// 检查 Blob 是否已存在（HEAD 请求）
+    pub async fn blob_exists(&self, repository: &str, digest: &str) -> Result<bool> {
+        let url = format!("{}/v2/{}/blobs/{}", self.base_url(), repository, digest);
+        let resp = self.authorize(self.client.head(&url)).send().await?;
+
+        match resp.status() {
+            StatusCode::OK => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => Err(anyhow!("Failed to check blob {}: {}", digest, status)),
+        }
+    }
+
+    /// 从同一 registry 的其他仓库跨仓库挂载 Blob
+    ///
+    /// Token 需要同时包含 `repository:<from>:pull`，否则 registry 会忽略挂载请求。
+    /// 返回 `false` 表示 registry 未执行挂载，调用方需要自行上传。
+    pub async fn mount_blob(&self, repository: &str, digest: &str, from: &str) -> Result<bool> {
+        let url = format!(
+            "{}/v2/{}/blobs/uploads/?mount={}&from={}",
+            self.base_url(),
+            repository,
+            digest,
+            from
+        );
+        let resp = self
+            .authorize(self.client.post(&url))
+            .header(header::CONTENT_LENGTH, 0)
+            .send()
+            .await?;
+
+        match resp.status() {
+            StatusCode::CREATED => Ok(true),
+            // registry 未挂载时会退化为普通上传会话，取消该会话以免遗留
+            StatusCode::ACCEPTED => {
+                if resp.headers().contains_key(header::LOCATION) {
+                    let location = self.upload_location(&resp, &url)?;
+                    let _ = self.authorize(self.client.delete(&location)).send().await;
+                }
+                Ok(false)
+            }
+            status => {
+                let error_text = resp.text().await.unwrap_or_default();
+                Err(anyhow!("Failed to mount blob {}: {} - {}", digest, status, error_text))
+            }
+        }
+    }
+
+    /// 单次上传 Blob（POST + PUT ?digest=）
+    pub async fn upload_blob_monolithic(
+        &self,
+        repository: &str,
+        digest: &str,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let location = self.start_upload(repository).await?;
+        let url = append_query(&location, &format!("digest={}", digest));
+
+        let resp = self
+            .authorize(self.client.put(&url))
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .header(header::CONTENT_LENGTH, data.len())
+            .body(data)
+            .send()
+            .await?;
+
+        if resp.status() != StatusCode::CREATED {
+            let status = resp.status();
+            let error_text = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to upload blob {}: {} - {}", digest, status, error_text));
+        }
+
+        Ok(())
+    }
+
+    /// 以流的方式单次上传 Blob，`size` 必须与流的总长度一致
+    pub async fn upload_blob_stream(
+        &self,
+        repository: &str,
+        digest: &str,
+        size: u64,
+        body: Body,
+    ) -> Result<()> {
+        let location = self.start_upload(repository).await?;
+        let url = append_query(&location, &format!("digest={}", digest));
+
+        let resp = self
+            .authorize(self.client.put(&url))
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .header(header::CONTENT_LENGTH, size)
+            .body(body)
+            .send()
+            .await?;
+
+        if resp.status() != StatusCode::CREATED {
+            let status = resp.status();
+            let error_text = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to upload blob {}: {} - {}", digest, status, error_text));
+        }
+
+        Ok(())
+    }
+
+    /// 分块上传 Blob（POST + PATCH... + PUT ?digest=）
+    pub async fn upload_blob_chunked(
+        &self,
+        repository: &str,
+        digest: &str,
+        input_path: &Path,
+        chunk_size: usize,
+    ) -> Result<()> {
+        let mut location = self.start_upload(repository).await?;
+        let mut file = File::open(input_path).await?;
+        let mut buffer = vec![0u8; chunk_size];
+        let mut offset: u64 = 0;
+
+        loop {
+            let n = read_full(&mut file, &mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+
+            let resp = self
+                .authorize(self.client.patch(&location))
+                .header(header::CONTENT_TYPE, "application/octet-stream")
+                .header(header::CONTENT_LENGTH, n)
+                .header(header::CONTENT_RANGE, format!("{}-{}", offset, offset + n as u64 - 1))
+                .body(buffer[..n].to_vec())
+                .send()
+                .await?;
+
+            if resp.status() != StatusCode::ACCEPTED {
+                let status = resp.status();
+                let error_text = resp.text().await.unwrap_or_default();
+                return Err(anyhow!("Failed to upload chunk of {}: {} - {}", digest, status, error_text));
+            }
+
+            // 每个分块之后 registry 都可能返回新的上传地址
+            location = self.upload_location(&resp, &location)?;
+            offset += n as u64;
+        }
+
+        let url = append_query(&location, &format!("digest={}", digest));
+        let resp = self
+            .authorize(self.client.put(&url))
+            .header(header::CONTENT_LENGTH, 0)
+            .send()
+            .await?;
+
+        if resp.status() != StatusCode::CREATED {
+            let status = resp.status();
+            let error_text = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to complete upload of {}: {} - {}", digest, status, error_text));
+        }
+
+        eprintln!("Blob uploaded: {} ({} bytes)", digest, offset);
+
+        Ok(())
+    }
+
+    /// 上传 Manifest，返回 registry 计算的 digest
+    pub async fn put_manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+        media_type: &str,
+        body: Vec<u8>,
+    ) -> Result<String> {
+        let url = format!("{}/v2/{}/manifests/{}", self.base_url(), repository, reference);
+
+        let resp = self
+            .authorize(self.client.put(&url))
+            .header(header::CONTENT_TYPE, media_type)
+            .body(body)
+            .send()
+            .await?;
+
+        if resp.status() != StatusCode::CREATED {
+            let status = resp.status();
+            let error_text = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to put manifest: {} - {}", status, error_text));
+        }
+
+        let digest = resp
+            .headers()
+            .get("Docker-Content-Digest")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+
+        Ok(digest)
+    }
+
+    /// 发起上传会话，返回上传地址
+    async fn start_upload(&self, repository: &str) -> Result<String> {
+        let url = format!("{}/v2/{}/blobs/uploads/", self.base_url(), repository);
+        let resp = self
+            .authorize(self.client.post(&url))
+            .header(header::CONTENT_LENGTH, 0)
+            .send()
+            .await?;
+
+        if resp.status() != StatusCode::ACCEPTED {
+            let status = resp.status();
+            let error_text = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to start upload: {} - {}", status, error_text));
+        }
+
+        self.upload_location(&resp, &url)
+    }
+
+    /// 从响应的 Location 头解析上传地址（可能是相对路径）
+    fn upload_location(&self, resp: &Response, fallback: &str) -> Result<String> {
+        let location = match resp.headers().get(header::LOCATION) {
+            Some(value) => value.to_str()?,
+            None => return Ok(fallback.to_string()),
+        };
+
+        if location.starts_with("http://") || location.starts_with("https://") {
+            Ok(location.to_string())
+        } else {
+            Ok(format!("{}{}", self.base_url(), location))
+        }
+    }
+
+    /// 添加 Bearer Token
+    fn authorize(&self, req: RequestBuilder) -> RequestBuilder {
+        match &self.token {
+            Some(token) => req.header(header::AUTHORIZATION, format!("Bearer {}", token)),
+            None => req,
+        }
+    }
+
+    /// Registry 地址（不含协议）
+    pub fn registry(&self) -> &str {
+        &self.registry
+    }
+
+    fn base_url(&self) -> String {
+        format!("{}://{}", self.scheme, self.registry)
+    }
+}
+
+/// 本地 registry（如 `localhost:5000` 上的 registry:2）使用 HTTP，
+/// `REGISTRY_INSECURE` 中列出的地址同样使用 HTTP
+fn registry_scheme(registry: &str) -> &'static str {
+    let host = registry.split(':').next().unwrap_or(registry);
+    if host == "localhost" || host == "127.0.0.1" {
+        return "http";
+    }
+
+    let insecure = std::env::var("REGISTRY_INSECURE").unwrap_or_default();
+    if insecure.split(',').any(|r| r.trim() == registry) {
+        "http"
+    } else {
+        "https"
+    }
+}
+
+/// 在 URL 后追加查询参数
+fn append_query(url: &str, query: &str) -> String {
+    if url.contains('?') {
+        format!("{}&{}", url, query)
+    } else {
+        format!("{}?{}", url, query)
+    }
+}
+
+/// 尽量填满缓冲区，返回实际读取的字节数（0 表示 EOF）
+async fn read_full(file: &mut File, buffer: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let n = file.read(&mut buffer[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
 }
 
 /// 解析 WWW-Authenticate 头
@@ -304,3 +640,173 @@ pub fn parse_image_ref(image_ref: &str) -> Result<ImageReference> {
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Request, Response, TestServer};
+
+    fn client(server: &TestServer) -> RegistryClient {
+        let registry = server.url.trim_start_matches("http://").to_string();
+        RegistryClient::new(registry, None, None).unwrap()
+    }
+
+    /// 返回 401 挑战，token 端点按是否携带 Basic Auth 发放不同的 token
+    fn token_server(request: &Request) -> Response {
+        match request.path() {
+            "/v2/" => {
+                let realm = format!("http://{}/token", request.header("host").unwrap());
+                Response::status(401, "")
+                    .with_header("WWW-Authenticate", &format!(r#"Bearer realm="{}",service="registry""#, realm))
+            }
+            "/token" if request.header("authorization").is_some() => Response::ok(r#"{"token":"push-token"}"#),
+            "/token" => Response::ok(r#"{"token":"anonymous-token"}"#),
+            _ => Response::status(404, ""),
+        }
+    }
+
+    #[tokio::test]
+    async fn authenticate_uses_credentials_when_configured() {
+        let server = TestServer::start(token_server).await;
+        let registry = server.url.trim_start_matches("http://").to_string();
+        let mut client = RegistryClient::new(registry, Some("user".to_string()), Some("secret".to_string())).unwrap();
+
+        let token = client
+            .authenticate("app", "repository:app:pull,push repository:base:pull")
+            .await
+            .unwrap();
+        assert_eq!(token, "push-token");
+
+        let requests = server.requests();
+        let token_request = requests.iter().find(|r| r.path() == "/token").unwrap();
+        assert_eq!(token_request.header("authorization"), Some("Basic dXNlcjpzZWNyZXQ="));
+        assert_eq!(
+            token_request.target,
+            "/token?service=registry&scope=repository:app:pull,push&scope=repository:base:pull"
+        );
+    }
+
+    #[tokio::test]
+    async fn authenticate_anonymously_without_credentials() {
+        let server = TestServer::start(token_server).await;
+        let token = client(&server)
+            .authenticate("library/nginx", "repository:library/nginx:pull")
+            .await
+            .unwrap();
+        assert_eq!(token, "anonymous-token");
+    }
+
+    #[tokio::test]
+    async fn blob_exists_checks_head() {
+        let server = TestServer::start(|request| match request.path() {
+            "/v2/app/blobs/sha256:aaa" => Response::ok(""),
+            _ => Response::status(404, ""),
+        })
+        .await;
+        let client = client(&server);
+
+        assert!(client.blob_exists("app", "sha256:aaa").await.unwrap());
+        assert!(!client.blob_exists("app", "sha256:bbb").await.unwrap());
+        assert!(server.requests().iter().all(|r| r.method == "HEAD"));
+    }
+
+    #[tokio::test]
+    async fn monolithic_upload_puts_digest_to_upload_location() {
+        let server = TestServer::start(|request| match request.method.as_str() {
+            "POST" => Response::status(202, "").with_header("Location", "/v2/app/blobs/uploads/session-1?state=x"),
+            "PUT" => Response::status(201, ""),
+            _ => Response::status(400, ""),
+        })
+        .await;
+
+        client(&server)
+            .upload_blob_monolithic("app", "sha256:abc", b"blob data".to_vec())
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].target, "/v2/app/blobs/uploads/");
+        assert_eq!(requests[1].target, "/v2/app/blobs/uploads/session-1?state=x&digest=sha256:abc");
+        assert_eq!(requests[1].header("content-type"), Some("application/octet-stream"));
+        assert_eq!(requests[1].body, b"blob data");
+    }
+
+    #[tokio::test]
+    async fn chunked_upload_sends_content_ranges() {
+        let server = TestServer::start(|request| match request.method.as_str() {
+            "POST" => Response::status(202, "").with_header("Location", "/v2/app/blobs/uploads/s0"),
+            "PATCH" => {
+                // 每个分块之后返回新的上传地址
+                let next = request.path().trim_start_matches("/v2/app/blobs/uploads/s").parse::<u32>().unwrap() + 1;
+                Response::status(202, "").with_header("Location", &format!("/v2/app/blobs/uploads/s{}", next))
+            }
+            "PUT" => Response::status(201, ""),
+            _ => Response::status(400, ""),
+        })
+        .await;
+
+        let path = std::env::temp_dir().join(format!("chunked-upload-{}", std::process::id()));
+        std::fs::write(&path, b"0123456789").unwrap();
+        client(&server)
+            .upload_blob_chunked("app", "sha256:abc", &path, 4)
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let requests = server.requests();
+        let patches: Vec<_> = requests
+            .iter()
+            .filter(|r| r.method == "PATCH")
+            .map(|r| (r.path(), r.header("content-range").unwrap(), r.body.as_slice()))
+            .collect();
+        assert_eq!(
+            patches,
+            [
+                ("/v2/app/blobs/uploads/s0", "0-3", b"0123".as_slice()),
+                ("/v2/app/blobs/uploads/s1", "4-7", b"4567".as_slice()),
+                ("/v2/app/blobs/uploads/s2", "8-9", b"89".as_slice()),
+            ]
+        );
+        let put = requests.last().unwrap();
+        assert_eq!(put.method, "PUT");
+        assert_eq!(put.target, "/v2/app/blobs/uploads/s3?digest=sha256:abc");
+    }
+
+    #[tokio::test]
+    async fn mount_blob_reports_created_and_cancels_fallback_session() {
+        let server = TestServer::start(|request| match (request.method.as_str(), request.query("mount").as_deref()) {
+            ("POST", Some("sha256:shared")) => Response::status(201, ""),
+            ("POST", _) => Response::status(202, "").with_header("Location", "/v2/app/blobs/uploads/orphan"),
+            ("DELETE", _) => Response::status(204, ""),
+            _ => Response::status(400, ""),
+        })
+        .await;
+        let client = client(&server);
+
+        assert!(client.mount_blob("app", "sha256:shared", "base").await.unwrap());
+        assert!(!client.mount_blob("app", "sha256:other", "base").await.unwrap());
+
+        let requests = server.requests();
+        assert_eq!(requests[0].target, "/v2/app/blobs/uploads/?mount=sha256:shared&from=base");
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].method, "DELETE");
+        assert_eq!(requests[2].target, "/v2/app/blobs/uploads/orphan");
+    }
+
+    #[tokio::test]
+    async fn put_manifest_returns_registry_digest() {
+        let server = TestServer::start(|_| Response::status(201, "").with_header("Docker-Content-Digest", "sha256:m")).await;
+
+        let digest = client(&server)
+            .put_manifest("app", "v1", MEDIA_TYPE_OCI_MANIFEST, b"{}".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(digest, "sha256:m");
+
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.target, "/v2/app/manifests/v1");
+        assert_eq!(request.header("content-type"), Some(MEDIA_TYPE_OCI_MANIFEST));
+    }
+}
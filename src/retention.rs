@@ -0,0 +1,227 @@
+use crate::destination::Destination;
+use crate::sidecar::{ArchiveMetadata, CHECKSUM_SUFFIX, METADATA_SUFFIX};
+use crate::tar::sanitize_filename;
+use crate::volume::INDEX_SUFFIX;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// 归档类型后缀，同一镜像的不同类型分别保留
+const ARCHIVE_KINDS: [&str; 3] = [".rootfs.tar.gz", ".delta.tar.gz", ".tar.gz"];
+
+/// 远程目录的保留策略
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// 每个镜像保留最近的 N 个归档
+    pub keep_last: Option<usize>,
+    /// 删除早于 D 天的归档
+    pub max_age_days: Option<u64>,
+    /// 只列出将被删除的归档
+    pub dry_run: bool,
+}
+
+impl RetentionPolicy {
+    /// 读取 `<PREFIX>_RETENTION_KEEP` 与 `<PREFIX>_RETENTION_DAYS`，都未设置时返回 None
+    pub fn from_env(prefix: &str) -> Result<Option<Self>> {
+        let read = |name: String| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Self::from_values(
+            read(format!("{}_RETENTION_KEEP", prefix)),
+            read(format!("{}_RETENTION_DAYS", prefix)),
+            [&format!("{}_RETENTION_KEEP", prefix), &format!("{}_RETENTION_DAYS", prefix)],
+        )
+    }
+
+    /// 由数量与天数构造（来自环境变量或目标 URL 的查询参数），都未设置时返回 None
+    ///
+    /// `names` 为两个值的来源，用于错误信息。
+    pub fn from_values(keep: Option<String>, days: Option<String>, names: [&str; 2]) -> Result<Option<Self>> {
+        let parse = |value: Option<String>, name: &str| -> Result<Option<u64>> {
+            value
+                .map(|v| v.parse().map_err(|_| anyhow!("Invalid {}: {}", name, v)))
+                .transpose()
+        };
+
+        let keep_last = parse(keep, names[0])?;
+        let max_age_days = parse(days, names[1])?;
+        if keep_last.is_none() && max_age_days.is_none() {
+            return Ok(None);
+        }
+        if keep_last == Some(0) {
+            return Err(anyhow!("{} must be at least 1", names[0]));
+        }
+
+        Ok(Some(Self {
+            keep_last: keep_last.map(|n| n as usize),
+            max_age_days,
+            dry_run: std::env::var("RETENTION_DRY_RUN").is_ok_and(|v| v == "true" || v == "1"),
+        }))
+    }
+}
+
+/// 按保留策略清理目标根目录中的旧归档，`current` 为本次上传的归档
+///
+/// 归档按元数据（`.json`）中的镜像分组，没有元数据时按文件名分组并使用远程文件的修改时间。
+pub async fn apply(destination: &dyn Destination, policy: &RetentionPolicy, current: &str) -> Result<()> {
+    let listing = destination.list().await?;
+    let names: Vec<String> = listing.iter().map(|f| f.name.clone()).collect();
+
+    let mut archives = Vec::new();
+    for (name, files) in group_files(&names) {
+        let metadata_file = format!("{}{}", name, METADATA_SUFFIX);
+        let metadata: Option<ArchiveMetadata> = if files.contains(&metadata_file) {
+            serde_json::from_slice(&destination.get(&metadata_file).await?).ok()
+        } else {
+            None
+        };
+
+        let created = match &metadata {
+            Some(metadata) => DateTime::parse_from_rfc3339(&metadata.created).ok().map(|c| c.to_utc()),
+            // 列目录时没有修改时间（FTP NLST）则单独查询
+            None => match listing.iter().find(|f| f.name == files[0]).and_then(|f| f.modified) {
+                Some(modified) => Some(modified),
+                None => destination.stat(&files[0]).await?.and_then(|f| f.modified),
+            },
+        };
+        archives.push(RemoteArchive {
+            group: group_key(&name, metadata.as_ref()),
+            name,
+            created,
+            files,
+        });
+    }
+
+    let expired = select(&archives, policy, current, Utc::now());
+    print_plan(&destination.location(), &expired, policy.dry_run);
+    if !policy.dry_run {
+        for archive in expired {
+            for file in &archive.files {
+                destination
+                    .delete(file)
+                    .await
+                    .with_context(|| format!("Failed to delete {}", file))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 远程目录中的一个归档及其分卷、附属文件
+#[derive(Debug)]
+pub struct RemoteArchive {
+    /// 归档文件名
+    pub name: String,
+    /// 属于同一组的归档按保留策略一起计算
+    pub group: String,
+    /// 元数据中的生成时间，没有元数据时为远程文件的修改时间
+    pub created: Option<DateTime<Utc>>,
+    pub files: Vec<String>,
+}
+
+/// 按归档文件名对目录中的文件分组：`<archive>`、`.001`……、`.volumes.json`、`.sha256`、`.json`
+///
+/// 上传中的临时文件（`.` 开头）与无法识别的文件不参与。
+pub fn group_files(names: &[String]) -> BTreeMap<String, Vec<String>> {
+    let mut archives: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for name in names.iter().filter(|n| !n.starts_with('.')) {
+        if let Some(archive) = archive_name(name) {
+            archives.entry(archive.to_string()).or_default().push(name.clone());
+        }
+    }
+    archives
+}
+
+/// 文件所属的归档名
+fn archive_name(name: &str) -> Option<&str> {
+    let base = [INDEX_SUFFIX, CHECKSUM_SUFFIX, METADATA_SUFFIX]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name);
+
+    // 分卷：`<archive>.001`
+    let base = match base.rsplit_once('.') {
+        Some((archive, n)) if n.len() == 3 && n.bytes().all(|b| b.is_ascii_digit()) => archive,
+        _ => base,
+    };
+
+    base.ends_with(".tar.gz").then_some(base)
+}
+
+/// 归档的分组键：镜像引用去掉 tag / digest 后按文件名规则转换
+///
+/// 优先使用元数据中的镜像引用；没有元数据时按文件名去掉最后一段（通常为 tag）。
+pub fn group_key(archive: &str, metadata: Option<&ArchiveMetadata>) -> String {
+    let (stem, kind) = ARCHIVE_KINDS
+        .iter()
+        .find_map(|kind| archive.strip_suffix(kind).map(|stem| (stem, *kind)))
+        .unwrap_or((archive, ""));
+
+    let image = match metadata {
+        Some(metadata) => sanitize_filename(strip_tag(&metadata.image)),
+        None => stem.rsplit_once('_').map_or(stem, |(image, _)| image).to_string(),
+    };
+
+    format!("{}{}", image, kind)
+}
+
+/// 去掉镜像引用中的 tag 与 digest
+fn strip_tag(image: &str) -> &str {
+    let image = image.split('@').next().unwrap_or(image);
+    match image.rfind(':') {
+        Some(pos) if !image[pos..].contains('/') => &image[..pos],
+        _ => image,
+    }
+}
+
+/// 按策略选出需要删除的归档；`current` 为本次上传的归档，始终保留
+pub fn select<'a>(
+    archives: &'a [RemoteArchive],
+    policy: &RetentionPolicy,
+    current: &str,
+    now: DateTime<Utc>,
+) -> Vec<&'a RemoteArchive> {
+    let mut groups: BTreeMap<&str, Vec<&RemoteArchive>> = BTreeMap::new();
+    for archive in archives {
+        groups.entry(&archive.group).or_default().push(archive);
+    }
+
+    let mut expired = Vec::new();
+    for (_, mut group) in groups {
+        // 新的在前；时间未知的视为最旧
+        group.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| b.name.cmp(&a.name)));
+
+        for (idx, archive) in group.into_iter().enumerate() {
+            if archive.name == current {
+                continue;
+            }
+            let over_count = policy.keep_last.is_some_and(|keep| idx >= keep);
+            let too_old = match (policy.max_age_days, archive.created) {
+                (Some(days), Some(created)) => now - created > chrono::Duration::days(days as i64),
+                _ => false,
+            };
+            if over_count || too_old {
+                expired.push(archive);
+            }
+        }
+    }
+
+    expired
+}
+
+/// 打印将要删除的归档
+pub fn print_plan(destination: &str, expired: &[&RemoteArchive], dry_run: bool) {
+    if expired.is_empty() {
+        eprintln!("Retention: nothing to delete on {}", destination);
+        return;
+    }
+
+    let action = if dry_run { "would delete" } else { "deleting" };
+    eprintln!("Retention: {} {} archives on {}", action, expired.len(), destination);
+    for archive in expired {
+        let created = archive
+            .created
+            .map(|c| c.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        eprintln!("  - {} ({}, {} files)", archive.name, created, archive.files.len());
+    }
+}
@@ -0,0 +1,484 @@
+use crate::compression;
+use crate::types::ManifestResponse;
+use crate::volume::ArchiveOutput;
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tar::{Archive, Builder, EntryType, Header};
+
+/// whiteout 文件前缀：`.wh.<name>` 表示删除下层的 `<name>`
+pub const WHITEOUT_PREFIX: &str = ".wh.";
+/// opaque 目录标记：隐藏下层该目录中的所有内容
+pub const WHITEOUT_OPAQUE: &str = ".wh..wh..opq";
+
+/// 将下载目录中的镜像 layers 合并为一个根文件系统 tar
+///
+/// layers 自顶向下处理：上层已经输出或删除的路径会跳过下层的同名条目，
+/// 因此每个路径只输出一次，不需要在磁盘上解包，也不需要 root 权限。
+/// 输出文件以 `.gz` 结尾时使用 gzip 压缩，设置 `volume_size` 时切分为分卷。
+pub fn export_rootfs(image_dir: &Path, output_file: &Path, volume_size: Option<u64>) -> Result<Vec<PathBuf>> {
+    eprintln!("Exporting root filesystem: {}", output_file.display());
+
+    let manifest: ManifestResponse =
+        serde_json::from_slice(&std::fs::read(image_dir.join("manifest.json"))?)?;
+    let blobs_dir = image_dir.join("blobs");
+
+    let output = ArchiveOutput::create(output_file, volume_size)?;
+    let output = if output_file.extension().is_some_and(|e| e == "gz") {
+        flatten_layers(&manifest, &blobs_dir, GzEncoder::new(output, Compression::default()))?.finish()?
+    } else {
+        flatten_layers(&manifest, &blobs_dir, output)?
+    };
+
+    let files = output.finish()?;
+    eprintln!("Root filesystem exported successfully!");
+
+    Ok(files)
+}
+
+/// 自顶向下合并所有 layers 写入 `writer`
+fn flatten_layers<W: Write>(manifest: &ManifestResponse, blobs_dir: &Path, writer: W) -> Result<W> {
+    let mut flattener = Flattener::new(Builder::new(writer));
+    for (idx, layer) in manifest.layers.iter().enumerate().rev() {
+        eprintln!(
+            "Flattening layer {}/{}: {}",
+            idx + 1,
+            manifest.layers.len(),
+            layer.digest
+        );
+        let layer_path = blobs_dir.join(&layer.digest);
+        flattener.add_layer(|| compression::open_layer(&layer_path, layer.media_type.as_deref()))?;
+    }
+
+    flattener.finish()
+}
+
+/// 待输出的硬链接
+struct PendingLink {
+    path: String,
+    header: Header,
+}
+
+/// 路径过滤条件
+type PathFilter = Box<dyn Fn(&str) -> bool>;
+
+/// 自顶向下合并 layers 的状态
+pub struct Flattener<W: Write> {
+    builder: Builder<W>,
+    /// 已经决定（输出或被删除）的路径
+    seen: HashSet<String>,
+    /// 已输出的非目录路径，下层中它们之下的条目都不可见
+    non_dirs: HashSet<String>,
+    /// 上层删除的路径
+    whiteouts: HashSet<String>,
+    /// 上层标记为 opaque 的目录
+    opaque_dirs: HashSet<String>,
+    /// 目标还未输出的硬链接，按目标路径分组
+    pending_links: HashMap<String, Vec<PendingLink>>,
+    /// 只输出匹配的路径（为空时输出全部）
+    filter: Option<PathFilter>,
+}
+
+impl<W: Write> Flattener<W> {
+    pub fn new(builder: Builder<W>) -> Self {
+        Self {
+            builder,
+            seen: HashSet::new(),
+            non_dirs: HashSet::new(),
+            whiteouts: HashSet::new(),
+            opaque_dirs: HashSet::new(),
+            pending_links: HashMap::new(),
+            filter: None,
+        }
+    }
+
+    /// 只输出满足条件的路径；可见性仍按全部条目计算
+    pub fn with_filter(mut self, filter: impl Fn(&str) -> bool + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// 已处理的 layers 是否已经完全决定了 `path` 的内容，下层不会再产生影响
+    ///
+    /// 路径被删除、是非目录条目或是 opaque 目录时返回 true。
+    pub fn is_resolved(&self, path: &str) -> bool {
+        self.is_hidden(path) || self.non_dirs.contains(path) || self.opaque_dirs.contains(path)
+    }
+
+    /// 是否还有等待下层目标的硬链接
+    pub fn has_pending_links(&self) -> bool {
+        !self.pending_links.is_empty()
+    }
+
+    /// 处理一个 layer（必须按自顶向下的顺序调用）
+    ///
+    /// `open_layer` 返回解压后的 layer 流。硬链接的目标出现在同一 layer 的前面
+    /// 却没有被输出时（被上层覆盖或未被选中），需要再读一遍该 layer 取出目标内容。
+    pub fn add_layer<R: Read>(&mut self, mut open_layer: impl FnMut() -> Result<R>) -> Result<()> {
+        // 同一 layer 中的 whiteout 只作用于下层
+        let mut layer_whiteouts = Vec::new();
+        let mut layer_opaque_dirs = Vec::new();
+        let mut layer_emitted = HashSet::new();
+        let mut layer_files = HashSet::new();
+        let mut relinks: HashMap<String, Vec<PendingLink>> = HashMap::new();
+
+        let mut archive = Archive::new(open_layer()?);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = normalize_path(&entry.path()?.to_string_lossy());
+            if path.is_empty() {
+                continue;
+            }
+
+            let (parent, name) = split_path(&path);
+            if name == WHITEOUT_OPAQUE {
+                layer_opaque_dirs.push(parent.to_string());
+                continue;
+            }
+            if let Some(deleted) = name.strip_prefix(WHITEOUT_PREFIX) {
+                layer_whiteouts.push(join_path(parent, deleted));
+                continue;
+            }
+
+            let mut header = entry.header().clone();
+            let entry_type = header.entry_type();
+            if entry_type.is_file() {
+                layer_files.insert(path.clone());
+            }
+
+            if self.seen.contains(&path) || self.is_hidden(&path) {
+                // 被上层覆盖或删除，但仍可能是上层硬链接的目标
+                if entry_type.is_file() {
+                    if let Some(links) = self.pending_links.remove(&path) {
+                        self.emit_links_as_file(links, &mut header, &mut entry)?;
+                    }
+                }
+                continue;
+            }
+
+            self.seen.insert(path.clone());
+            if entry_type != EntryType::Directory {
+                self.non_dirs.insert(path.clone());
+            }
+
+            let selected = self.is_selected(&path);
+
+            match entry_type {
+                EntryType::Link if !selected => {}
+                EntryType::Link => {
+                    let target = normalize_path(
+                        &entry
+                            .link_name()?
+                            .map(|t| t.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                    );
+                    if layer_emitted.contains(&target) {
+                        self.builder.append_link(&mut header, &path, &target)?;
+                    } else if layer_files.contains(&target) {
+                        // 目标在本 layer 中已经跳过，稍后重读本 layer 输出
+                        relinks.entry(target).or_default().push(PendingLink { path, header });
+                    } else {
+                        // 目标位于下层，等处理到目标时再输出
+                        self.pending_links
+                            .entry(target)
+                            .or_default()
+                            .push(PendingLink { path, header });
+                    }
+                }
+                EntryType::Symlink if !selected => {}
+                EntryType::Symlink => {
+                    let target = entry
+                        .link_name()?
+                        .map(|t| t.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    self.builder.append_link(&mut header, &path, target)?;
+                    layer_emitted.insert(path);
+                }
+                _ if !selected => {
+                    // 未选中的文件仍可能是选中硬链接的目标
+                    if let Some(links) = self.pending_links.remove(&path) {
+                        self.emit_links_as_file(links, &mut header, &mut entry)?;
+                    }
+                }
+                _ => {
+                    self.builder.append_data(&mut header, &path, &mut entry)?;
+                    if let Some(links) = self.pending_links.remove(&path) {
+                        for mut link in links {
+                            self.builder.append_link(&mut link.header, &link.path, &path)?;
+                        }
+                    }
+                    layer_emitted.insert(path);
+                }
+            }
+        }
+
+        if !relinks.is_empty() {
+            let mut archive = Archive::new(open_layer()?);
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                let path = normalize_path(&entry.path()?.to_string_lossy());
+                if let Some(links) = relinks.remove(&path) {
+                    let mut header = entry.header().clone();
+                    self.emit_links_as_file(links, &mut header, &mut entry)?;
+                }
+            }
+        }
+
+        self.whiteouts.extend(layer_whiteouts);
+        self.opaque_dirs.extend(layer_opaque_dirs);
+
+        Ok(())
+    }
+
+    /// 完成输出；目标始终未出现的硬链接会被丢弃
+    pub fn finish(mut self) -> Result<W> {
+        for (target, links) in &self.pending_links {
+            for link in links {
+                eprintln!(
+                    "Warning: dropping hardlink {} -> {} (target not found)",
+                    link.path, target
+                );
+            }
+        }
+
+        self.builder.finish()?;
+        Ok(self.builder.into_inner()?)
+    }
+
+    /// 目标在合并结果中不可见时，把第一个链接输出为普通文件，其余链接指向它
+    fn emit_links_as_file<R: Read>(
+        &mut self,
+        links: Vec<PendingLink>,
+        target_header: &mut Header,
+        data: &mut R,
+    ) -> Result<()> {
+        let mut links = links.into_iter();
+        let first = match links.next() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+
+        self.builder.append_data(target_header, &first.path, data)?;
+        for mut link in links {
+            self.builder.append_link(&mut link.header, &link.path, &first.path)?;
+        }
+
+        Ok(())
+    }
+
+    fn is_selected(&self, path: &str) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter(path))
+    }
+
+    /// 路径是否被上层的 whiteout、opaque 目录或非目录条目隐藏
+    fn is_hidden(&self, path: &str) -> bool {
+        if self.whiteouts.contains(path) {
+            return true;
+        }
+
+        let mut current = path;
+        while let Some(idx) = current.rfind('/') {
+            current = &current[..idx];
+            if self.whiteouts.contains(current)
+                || self.opaque_dirs.contains(current)
+                || self.non_dirs.contains(current)
+            {
+                return true;
+            }
+        }
+
+        // 根目录 opaque 时隐藏下层全部内容
+        self.opaque_dirs.contains("")
+    }
+}
+
+/// 统一路径格式：去掉开头的 `./` 与 `/` 以及结尾的 `/`
+pub fn normalize_path(path: &str) -> String {
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    path.trim_end_matches('/').to_string()
+}
+
+/// 拆分为父目录与文件名
+pub fn split_path(path: &str) -> (&str, &str) {
+    match path.rsplit_once('/') {
+        Some((parent, name)) => (parent, name),
+        None => ("", path),
+    }
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// 测试用的 layer 条目
+    pub(crate) enum Item<'a> {
+        Dir,
+        File(&'a str),
+        Link(&'a str),
+    }
+
+    /// 在内存中生成一个未压缩的 layer tar
+    pub(crate) fn layer(items: &[(&str, Item)]) -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for (path, item) in items {
+            let mut header = Header::new_gnu();
+            header.set_mode(0o644);
+            match item {
+                Item::Dir => {
+                    header.set_entry_type(EntryType::Directory);
+                    header.set_mode(0o755);
+                    header.set_size(0);
+                    builder.append_data(&mut header, path, std::io::empty()).unwrap();
+                }
+                Item::File(content) => {
+                    header.set_entry_type(EntryType::Regular);
+                    header.set_size(content.len() as u64);
+                    builder.append_data(&mut header, path, content.as_bytes()).unwrap();
+                }
+                Item::Link(target) => {
+                    header.set_entry_type(EntryType::Link);
+                    header.set_size(0);
+                    builder.append_link(&mut header, path, target).unwrap();
+                }
+            }
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// 自顶向下合并（`layers` 按镜像中的顺序，自底向上给出）
+    pub(crate) fn flatten<W: Write>(mut flattener: Flattener<W>, layers: &[Vec<u8>]) -> W {
+        for layer in layers.iter().rev() {
+            flattener.add_layer(|| Ok(Cursor::new(layer.as_slice()))).unwrap();
+        }
+        flattener.finish().unwrap()
+    }
+
+    /// 读取合并结果：路径、条目类型、内容或链接目标
+    fn entries(tar: &[u8]) -> Vec<(String, EntryType, String)> {
+        let mut archive = Archive::new(tar);
+        archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let path = entry.path().unwrap().to_string_lossy().to_string();
+                let entry_type = entry.header().entry_type();
+                let mut content = String::new();
+                if entry_type == EntryType::Link {
+                    content = entry.link_name().unwrap().unwrap().to_string_lossy().to_string();
+                } else {
+                    entry.read_to_string(&mut content).unwrap();
+                }
+                (path, entry_type, content)
+            })
+            .collect()
+    }
+
+    fn paths(tar: &[u8]) -> Vec<String> {
+        let mut paths: Vec<_> = entries(tar).into_iter().map(|(path, _, _)| path).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn whiteout_deletes_lower_file() {
+        let lower = layer(&[
+            ("etc", Item::Dir),
+            ("etc/a", Item::File("a")),
+            ("etc/b", Item::File("b")),
+        ]);
+        let upper = layer(&[("etc", Item::Dir), ("etc/.wh.a", Item::File(""))]);
+
+        let tar = flatten(Flattener::new(Builder::new(Vec::new())), &[lower, upper]);
+
+        assert_eq!(paths(&tar), ["etc", "etc/b"]);
+    }
+
+    #[test]
+    fn whiteout_only_applies_to_lower_layers() {
+        let lower = layer(&[("a", Item::File("old"))]);
+        let upper = layer(&[(".wh.a", Item::File("")), ("a", Item::File("new"))]);
+
+        let tar = flatten(Flattener::new(Builder::new(Vec::new())), &[lower, upper]);
+
+        assert_eq!(entries(&tar), [("a".to_string(), EntryType::Regular, "new".to_string())]);
+    }
+
+    #[test]
+    fn opaque_directory_hides_lower_contents() {
+        let lower = layer(&[
+            ("dir", Item::Dir),
+            ("dir/old", Item::File("old")),
+            ("dir/sub", Item::Dir),
+            ("dir/sub/deep", Item::File("deep")),
+            ("other", Item::File("other")),
+        ]);
+        let upper = layer(&[
+            ("dir", Item::Dir),
+            ("dir/.wh..wh..opq", Item::File("")),
+            ("dir/new", Item::File("new")),
+        ]);
+
+        let tar = flatten(Flattener::new(Builder::new(Vec::new())), &[lower, upper]);
+
+        assert_eq!(paths(&tar), ["dir", "dir/new", "other"]);
+    }
+
+    #[test]
+    fn hardlink_to_whited_out_target_becomes_file() {
+        let lower = layer(&[("a", Item::File("shared")), ("b", Item::Link("a"))]);
+        let upper = layer(&[(".wh.a", Item::File(""))]);
+
+        let tar = flatten(Flattener::new(Builder::new(Vec::new())), &[lower, upper]);
+
+        assert_eq!(entries(&tar), [("b".to_string(), EntryType::Regular, "shared".to_string())]);
+    }
+
+    #[test]
+    fn hardlinks_to_whited_out_target_share_first_link() {
+        let lower = layer(&[
+            ("a", Item::File("shared")),
+            ("b", Item::Link("a")),
+            ("c", Item::Link("a")),
+        ]);
+        let upper = layer(&[(".wh.a", Item::File(""))]);
+
+        let tar = flatten(Flattener::new(Builder::new(Vec::new())), &[lower, upper]);
+
+        assert_eq!(
+            entries(&tar),
+            [
+                ("b".to_string(), EntryType::Regular, "shared".to_string()),
+                ("c".to_string(), EntryType::Link, "b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn hardlink_to_lower_layer_target_is_kept() {
+        let lower = layer(&[("a", Item::File("shared"))]);
+        let upper = layer(&[("b", Item::Link("a"))]);
+
+        let tar = flatten(Flattener::new(Builder::new(Vec::new())), &[lower, upper]);
+
+        assert_eq!(
+            entries(&tar),
+            [
+                ("a".to_string(), EntryType::Regular, "shared".to_string()),
+                ("b".to_string(), EntryType::Link, "a".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use crate::digest;
+use crate::types::{Descriptor, ManifestResponse};
+use crate::volume;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 校验和文件后缀（`sha256sum -c` 格式）
+pub const CHECKSUM_SUFFIX: &str = ".sha256";
+/// 元数据文件后缀
+pub const METADATA_SUFFIX: &str = ".json";
+
+/// 归档元数据：接收端据此确认归档内容
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveMetadata {
+    /// 镜像引用
+    pub image: String,
+    /// 引用解析出的 Manifest digest（多架构时为 Index 的 digest）
+    pub manifest_digest: String,
+    /// 选中平台的 Manifest digest
+    pub platform_digest: String,
+    /// `os/architecture[/variant]`，config 不在归档中时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    pub config: Descriptor,
+    pub layers: Vec<Descriptor>,
+    pub archive: ArchiveInfo,
+    /// 生成时间（RFC 3339）
+    pub created: String,
+    /// 生成归档的工具及版本
+    pub tool: String,
+}
+
+/// 归档文件信息
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveInfo {
+    pub name: String,
+    pub size: u64,
+    pub digest: String,
+    /// 分卷数量，未分卷时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volumes: Option<usize>,
+}
+
+impl ArchiveMetadata {
+    pub fn new(
+        image: &str,
+        manifest_digest: &str,
+        platform_digest: &str,
+        manifest: &ManifestResponse,
+        platform: Option<String>,
+        archive: ArchiveInfo,
+    ) -> Self {
+        Self {
+            image: image.to_string(),
+            manifest_digest: manifest_digest.to_string(),
+            platform_digest: platform_digest.to_string(),
+            platform,
+            config: manifest.config.clone(),
+            layers: manifest.layers.clone(),
+            archive,
+            created: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            tool: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+/// 从镜像 config 中读取平台（`os/architecture[/variant]`）
+pub fn platform_of(config: &[u8]) -> Option<String> {
+    let config: serde_json::Value = serde_json::from_slice(config).ok()?;
+    let os = config.get("os")?.as_str()?;
+    let arch = config.get("architecture")?.as_str()?;
+
+    Some(match config.get("variant").and_then(|v| v.as_str()) {
+        Some(variant) => format!("{}/{}/{}", os, arch, variant),
+        None => format!("{}/{}", os, arch),
+    })
+}
+
+/// 统计本地归档（单个文件或分卷）的大小与 digest
+///
+/// `files` 为 `create_tar_archive` 的返回值；分卷模式下直接使用索引中的记录，不再读取分卷。
+pub fn archive_info(name: &str, files: &[PathBuf]) -> Result<ArchiveInfo> {
+    if let Some(index_file) = files.iter().find(|f| volume::is_index(f)) {
+        let index = volume::read_index(index_file)?;
+        return Ok(ArchiveInfo {
+            name: name.to_string(),
+            size: index.size,
+            digest: index.digest,
+            volumes: Some(index.volumes.len()),
+        });
+    }
+
+    let file = files.first().ok_or_else(|| anyhow!("No archive files"))?;
+    Ok(ArchiveInfo {
+        name: name.to_string(),
+        size: std::fs::metadata(file)?.len(),
+        digest: digest::sha256_file(file)?,
+        volumes: None,
+    })
+}
+
+/// 在 `dir` 中写入 `<archive>.sha256` 与 `<archive>.json`，返回两个文件的路径
+///
+/// 分卷模式下校验和文件列出每个分卷，接收端可以直接用 `sha256sum -c` 校验。
+pub fn write_sidecars(dir: &Path, files: &[PathBuf], metadata: &ArchiveMetadata) -> Result<Vec<PathBuf>> {
+    let mut checksums = String::new();
+    match files.iter().find(|f| volume::is_index(f)) {
+        Some(index_file) => {
+            for volume in volume::read_index(index_file)?.volumes {
+                checksums.push_str(&checksum_line(&volume.digest, &volume.name));
+            }
+        }
+        None => checksums.push_str(&checksum_line(&metadata.archive.digest, &metadata.archive.name)),
+    }
+
+    let checksum_path = dir.join(format!("{}{}", metadata.archive.name, CHECKSUM_SUFFIX));
+    std::fs::write(&checksum_path, checksums)?;
+
+    let metadata_path = dir.join(format!("{}{}", metadata.archive.name, METADATA_SUFFIX));
+    std::fs::write(&metadata_path, serde_json::to_string_pretty(metadata)?)?;
+
+    eprintln!("Checksum written: {}", checksum_path.display());
+    eprintln!("Metadata written: {}", metadata_path.display());
+
+    Ok(vec![checksum_path, metadata_path])
+}
+
+/// `sha256sum` 格式的一行：`<hex>  <name>`
+fn checksum_line(digest: &str, name: &str) -> String {
+    format!("{}  {}\n", digest.trim_start_matches("sha256:"), name)
+}
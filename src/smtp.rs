@@ -0,0 +1,350 @@
+use crate::notify::Report;
+use crate::sidecar::CHECKSUM_SUFFIX;
+use crate::tls::{self, MaybeTlsStream};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+/// 连接与命令超时
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// SMTP 加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// 明文（只用于内网中继）
+    None,
+    /// 连接后发送 `STARTTLS`（通常为 587 端口）
+    StartTls,
+    /// 连接即进行 TLS 握手（通常为 465 端口）
+    Implicit,
+}
+
+/// 邮件通知配置
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    /// 自签名服务器的 CA 证书（PEM）
+    pub ca_file: Option<PathBuf>,
+    /// 固定的服务器证书 SHA-256 指纹
+    pub pinned_cert: Option<String>,
+}
+
+impl SmtpConfig {
+    /// 从环境变量读取配置，未设置 `SMTP_SERVER` 时返回 None
+    pub fn from_env() -> Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let Some(host) = var("SMTP_SERVER") else {
+            return Ok(None);
+        };
+
+        let tls = match var("SMTP_TLS").as_deref() {
+            None | Some("starttls") => SmtpTls::StartTls,
+            Some("implicit") => SmtpTls::Implicit,
+            Some("none") => SmtpTls::None,
+            Some(other) => return Err(anyhow!("Invalid SMTP_TLS: {}", other)),
+        };
+
+        let port = match var("SMTP_PORT") {
+            Some(port) => port.parse().map_err(|_| anyhow!("Invalid SMTP_PORT: {}", port))?,
+            None if tls == SmtpTls::Implicit => 465,
+            None if tls == SmtpTls::StartTls => 587,
+            None => 25,
+        };
+
+        let to: Vec<String> = var("SMTP_TO")
+            .unwrap_or_default()
+            .split(',')
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty())
+            .collect();
+        if to.is_empty() {
+            return Err(anyhow!("SMTP_SERVER requires SMTP_TO"));
+        }
+
+        let username = var("SMTP_USERNAME");
+        let from = var("SMTP_FROM")
+            .or_else(|| username.clone())
+            .ok_or_else(|| anyhow!("SMTP_SERVER requires SMTP_FROM or SMTP_USERNAME"))?;
+
+        Ok(Some(Self {
+            host,
+            port,
+            tls,
+            username,
+            password: var("SMTP_PASSWORD"),
+            from,
+            to,
+            ca_file: var("SMTP_CA_CERT").map(PathBuf::from),
+            pinned_cert: var("SMTP_PINNED_CERT_SHA256"),
+        }))
+    }
+}
+
+/// 发送报告邮件；校验和文件既写在正文中，也作为内联附件
+pub async fn send(config: &SmtpConfig, report: &Report) -> Result<()> {
+    let message = compose(config, report);
+
+    let mut client = SmtpClient::connect(config).await?;
+    client.ehlo().await?;
+    if config.tls == SmtpTls::StartTls {
+        client = client.starttls(config).await?;
+        client.ehlo().await?;
+    }
+    if let Some(username) = &config.username {
+        client
+            .auth(username, config.password.as_deref().unwrap_or_default())
+            .await?;
+    }
+
+    client.command(&format!("MAIL FROM:<{}>", address(&config.from)), &[250]).await?;
+    for recipient in &config.to {
+        client.command(&format!("RCPT TO:<{}>", address(recipient)), &[250, 251]).await?;
+    }
+    client.command("DATA", &[354]).await?;
+    client.data(&message).await?;
+    let _ = client.command("QUIT", &[221]).await;
+
+    Ok(())
+}
+
+/// 生成 MIME 邮件（multipart/mixed：正文 + 校验和附件）
+fn compose(config: &SmtpConfig, report: &Report) -> String {
+    let boundary = format!("report-{:x}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default());
+    let host = config.from.rsplit_once('@').map_or("localhost", |(_, domain)| domain.trim_end_matches('>'));
+
+    let mut body = report.text();
+    if let Some(checksum) = &report.checksum {
+        body.push_str("\n\nSHA-256 checksums (verify with `sha256sum -c`):\n\n");
+        body.push_str(checksum);
+    }
+
+    let mut message = String::new();
+    message.push_str(&format!("From: {}\r\n", config.from));
+    message.push_str(&format!("To: {}\r\n", config.to.join(", ")));
+    message.push_str(&format!("Subject: {}\r\n", encode_header(&report.title())));
+    message.push_str(&format!("Date: {}\r\n", chrono::Utc::now().to_rfc2822()));
+    message.push_str(&format!(
+        "Message-ID: <{}.{}@{}>\r\n",
+        chrono::Utc::now().timestamp_millis(),
+        std::process::id(),
+        host
+    ));
+    message.push_str("MIME-Version: 1.0\r\n");
+    message.push_str(&format!("Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n", boundary));
+
+    message.push_str(&format!("--{}\r\n", boundary));
+    message.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+    message.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+    message.push_str(&base64_lines(body.as_bytes()));
+
+    if let (Some(checksum), Some(filename)) = (&report.checksum, &report.filename) {
+        let name = format!("{}{}", filename, CHECKSUM_SUFFIX);
+        message.push_str(&format!("--{}\r\n", boundary));
+        message.push_str(&format!("Content-Type: text/plain; charset=utf-8; name=\"{}\"\r\n", name));
+        message.push_str(&format!("Content-Disposition: inline; filename=\"{}\"\r\n", name));
+        message.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+        message.push_str(&base64_lines(checksum.as_bytes()));
+    }
+
+    message.push_str(&format!("--{}--\r\n", boundary));
+    message
+}
+
+/// 非 ASCII 的头部按 RFC 2047 编码
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+    format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(value))
+}
+
+/// Base64，每行 76 个字符
+fn base64_lines(data: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+    let mut lines = String::new();
+    for chunk in encoded.as_bytes().chunks(76) {
+        lines.push_str(&String::from_utf8_lossy(chunk));
+        lines.push_str("\r\n");
+    }
+    lines
+}
+
+/// `Name <user@example.com>` 中的地址部分
+fn address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+/// 最小化的 SMTP 客户端
+struct SmtpClient {
+    stream: BufReader<MaybeTlsStream>,
+    /// EHLO 返回的扩展（大写）
+    extensions: Vec<String>,
+}
+
+impl SmtpClient {
+    /// 连接服务器并读取欢迎信息；隐式 TLS 时先完成握手
+    async fn connect(config: &SmtpConfig) -> Result<Self> {
+        eprintln!("Connecting to SMTP server {}:{}...", config.host, config.port);
+        let stream = tokio::time::timeout(TIMEOUT, TcpStream::connect((config.host.as_str(), config.port)))
+            .await
+            .map_err(|_| anyhow!("Timed out connecting to {}:{}", config.host, config.port))??;
+
+        let stream = if config.tls == SmtpTls::Implicit {
+            MaybeTlsStream::Tls(Box::new(handshake(config, stream).await?))
+        } else {
+            MaybeTlsStream::Plain(stream)
+        };
+
+        let mut client = Self {
+            stream: BufReader::new(stream),
+            extensions: Vec::new(),
+        };
+        let (code, message) = client.read_reply().await?;
+        if code != 220 {
+            return Err(anyhow!("SMTP connect failed: {} {}", code, message.join(" ")));
+        }
+        Ok(client)
+    }
+
+    async fn ehlo(&mut self) -> Result<()> {
+        let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+        let lines = self.command(&format!("EHLO {}", hostname), &[250]).await?;
+        // 第一行是服务器的问候
+        self.extensions = lines.iter().skip(1).map(|l| l.to_ascii_uppercase()).collect();
+        Ok(())
+    }
+
+    /// `STARTTLS` 后在同一连接上握手
+    async fn starttls(mut self, config: &SmtpConfig) -> Result<Self> {
+        if !self.extensions.iter().any(|e| e == "STARTTLS") {
+            return Err(anyhow!("SMTP server does not support STARTTLS"));
+        }
+        self.command("STARTTLS", &[220]).await?;
+
+        let stream = match self.stream.into_inner() {
+            MaybeTlsStream::Plain(stream) => stream,
+            MaybeTlsStream::Tls(_) => return Err(anyhow!("TLS already established")),
+        };
+        Ok(Self {
+            stream: BufReader::new(MaybeTlsStream::Tls(Box::new(handshake(config, stream).await?))),
+            extensions: Vec::new(),
+        })
+    }
+
+    /// 认证：优先 `AUTH PLAIN`，不支持时使用 `AUTH LOGIN`
+    async fn auth(&mut self, username: &str, password: &str) -> Result<()> {
+        let encode = |s: &str| base64::engine::general_purpose::STANDARD.encode(s);
+        let mechanisms: Vec<&str> = self
+            .extensions
+            .iter()
+            .filter_map(|e| e.strip_prefix("AUTH").map(|m| m.trim_start_matches([' ', '='])))
+            .flat_map(|m| m.split_whitespace())
+            .collect();
+
+        if mechanisms.contains(&"PLAIN") || !mechanisms.contains(&"LOGIN") {
+            let credentials = encode(&format!("\0{}\0{}", username, password));
+            self.send(&format!("AUTH PLAIN {}", credentials), "AUTH PLAIN ****", &[235]).await?;
+        } else {
+            self.command("AUTH LOGIN", &[334]).await?;
+            self.send(&encode(username), "AUTH LOGIN ****", &[334]).await?;
+            self.send(&encode(password), "AUTH LOGIN ****", &[235]).await?;
+        }
+        Ok(())
+    }
+
+    /// 发送邮件内容（行首的 `.` 加倍），以 `<CRLF>.<CRLF>` 结束
+    async fn data(&mut self, message: &str) -> Result<()> {
+        let mut data = String::with_capacity(message.len() + 16);
+        for line in message.split("\r\n") {
+            if line.starts_with('.') {
+                data.push('.');
+            }
+            data.push_str(line);
+            data.push_str("\r\n");
+        }
+        data.truncate(data.len() - 2);
+        data.push_str(".\r\n");
+
+        let stream = self.stream.get_mut();
+        stream.write_all(data.as_bytes()).await?;
+        stream.flush().await?;
+
+        let (code, message) = self.read_reply().await?;
+        if code != 250 {
+            return Err(anyhow!("SMTP DATA failed: {} {}", code, message.join(" ")));
+        }
+        Ok(())
+    }
+
+    /// 发送命令并检查响应码，返回响应的各行
+    async fn command(&mut self, command: &str, codes: &[u16]) -> Result<Vec<String>> {
+        self.send(command, command, codes).await
+    }
+
+    /// 同 `command`，日志与错误中显示 `printable`（隐藏认证信息）
+    async fn send(&mut self, command: &str, printable: &str, codes: &[u16]) -> Result<Vec<String>> {
+        eprintln!("SMTP > {}", printable);
+
+        let stream = self.stream.get_mut();
+        stream.write_all(format!("{}\r\n", command).as_bytes()).await?;
+        stream.flush().await?;
+
+        let (code, lines) = self.read_reply().await?;
+        if !codes.contains(&code) {
+            let verb = printable.split_whitespace().next().unwrap_or_default();
+            return Err(anyhow!("SMTP {} failed: {} {}", verb, code, lines.join(" ")));
+        }
+        Ok(lines)
+    }
+
+    /// 读取一条响应（`250-` 开头的行表示后面还有）
+    async fn read_reply(&mut self) -> Result<(u16, Vec<String>)> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            let read = tokio::time::timeout(TIMEOUT, self.stream.read_line(&mut line))
+                .await
+                .map_err(|_| anyhow!("Timed out waiting for SMTP reply"))??;
+            if read == 0 {
+                return Err(anyhow!("SMTP connection closed by server"));
+            }
+
+            let line = line.trim_end_matches(['\r', '\n']);
+            let code: u16 = line
+                .get(..3)
+                .and_then(|c| c.parse().ok())
+                .ok_or_else(|| anyhow!("Invalid SMTP reply: {}", line))?;
+            lines.push(line.get(4..).unwrap_or_default().to_string());
+
+            if line.as_bytes().get(3) != Some(&b'-') {
+                eprintln!("SMTP < {} {}", code, lines[0]);
+                return Ok((code, lines));
+            }
+        }
+    }
+}
+
+async fn handshake(
+    config: &SmtpConfig,
+    stream: TcpStream,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>> {
+    let tls_config = tls::client_config(config.ca_file.as_deref(), config.pinned_cert.as_deref())?;
+    TlsConnector::from(tls_config)
+        .connect(tls::server_name(&config.host)?, stream)
+        .await
+        .context("TLS handshake failed")
+}
@@ -0,0 +1,311 @@
+use crate::destination::{ByteStream, Destination};
+use crate::download::{resolve_manifest, verify_signature};
+use crate::registry::{parse_image_ref, RegistryClient};
+use crate::sidecar;
+use crate::types::ManifestResponse;
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{BufWriter, Read, Write};
+use std::sync::Arc;
+use tar::{Builder, Header};
+use tokio::runtime::Handle;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::io::{StreamReader, SyncIoBridge};
+
+/// 每个目标缓冲的数据块数量（块大小约 1 MiB）
+const CHANNEL_CAPACITY: usize = 8;
+
+/// 流式上传结果
+#[derive(Debug)]
+pub struct StreamSummary {
+    /// 归档大小
+    pub size: u64,
+    /// 归档的 sha256 digest
+    pub digest: String,
+    /// 每个目标的远程路径
+    pub destinations: Vec<String>,
+    /// 引用解析出的 Manifest digest
+    pub manifest_digest: String,
+    /// 选中平台的 Manifest digest
+    pub platform_digest: String,
+    pub manifest: ManifestResponse,
+    /// 从 config 中读取的平台
+    pub platform: Option<String>,
+}
+
+/// 不落盘地下载镜像并上传归档
+///
+/// layers 按顺序从 registry 读取，直接写入 tar/gzip 编码器，输出同时发送给所有目标。
+/// 每个 Blob 在写入时计算 digest，不一致时中断所有上传。归档格式与下载模式相同。
+pub async fn stream_image(
+    image_ref: &str,
+    username: Option<String>,
+    password: Option<String>,
+    cosign_public_key: Option<&str>,
+    archive_name: &str,
+    destinations: Vec<Arc<dyn Destination>>,
+) -> Result<StreamSummary> {
+    if destinations.is_empty() {
+        return Err(anyhow!(
+            "STREAM_UPLOAD requires DESTINATIONS, FTP_SERVER, SFTP_SERVER, S3_BUCKET or HTTP_UPLOAD_URL"
+        ));
+    }
+
+    eprintln!("Parsing image reference: {}", image_ref);
+    let image = parse_image_ref(image_ref)?;
+
+    let scope = format!("repository:{}:pull", image.repository);
+    let mut client = RegistryClient::new(image.registry.clone(), username, password)?;
+
+    eprintln!("Authenticating...");
+    client.authenticate(&image.repository, &scope).await?;
+
+    let resolved = resolve_manifest(&client, &image.repository, &image.reference).await?;
+
+    if let Some(public_key) = cosign_public_key {
+        // 签名文件很小，只在临时目录中保存
+        let signature_dir = std::env::temp_dir().join(format!("cosign-{}", std::process::id()));
+        let result = verify_signature(
+            &client,
+            &image.repository,
+            &resolved.digest,
+            &resolved.platform_digest,
+            public_key,
+            &signature_dir,
+        )
+        .await;
+        let _ = tokio::fs::remove_dir_all(&signature_dir).await;
+        result?;
+    }
+
+    // 每个目标一个数据通道与一个摘要通道
+    let mut senders = Vec::new();
+    let mut summary_senders = Vec::new();
+    let mut uploads = Vec::new();
+    for destination in destinations {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let (summary_tx, summary_rx) = oneshot::channel();
+        senders.push(tx);
+        summary_senders.push(summary_tx);
+
+        let data: ByteStream = Box::pin(futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        }));
+        let archive_name = archive_name.to_string();
+        uploads.push(tokio::spawn(async move {
+            destination.put_stream(&archive_name, data, summary_rx).await
+        }));
+    }
+
+    let handle = Handle::current();
+    let repository = image.repository.clone();
+    let manifest = resolved.manifest.clone();
+    let manifest_bytes = resolved.manifest_bytes.clone();
+    let error_senders = senders.clone();
+    let builder = tokio::task::spawn_blocking(move || {
+        let result = build_archive(&handle, &client, &repository, &manifest, &manifest_bytes, FanoutWriter::new(senders));
+        if let Err(e) = &result {
+            // 通知所有目标中断上传
+            for sender in &error_senders {
+                let _ = sender.blocking_send(Err(std::io::Error::other(e.to_string())));
+            }
+        }
+        result
+    });
+
+    let built = builder.await?;
+    if let Ok((size, digest, _)) = &built {
+        for summary in summary_senders {
+            let _ = summary.send((*size, digest.clone()));
+        }
+    } else {
+        drop(summary_senders);
+    }
+
+    let mut destinations = Vec::new();
+    let mut upload_error = None;
+    for upload in uploads {
+        match upload.await? {
+            Ok(destination) => destinations.push(destination),
+            Err(e) => {
+                eprintln!("Upload failed: {:#}", e);
+                upload_error.get_or_insert(e);
+            }
+        }
+    }
+
+    // 目标失败时归档生成只会报告通道关闭，优先返回目标的错误
+    if let Some(e) = upload_error {
+        return Err(e);
+    }
+    let (size, digest, platform) = built?;
+
+    Ok(StreamSummary {
+        size,
+        digest,
+        destinations,
+        manifest_digest: resolved.digest,
+        platform_digest: resolved.platform_digest,
+        manifest: resolved.manifest,
+        platform,
+    })
+}
+
+/// 在阻塞线程中生成归档：Blob 边下载边写入，写入后校验 digest 与大小
+///
+/// `manifest_bytes` 为 registry 返回的原始 Manifest，原样写入 `manifest.json`。
+/// 返回归档大小、digest 以及从 config 中读取的平台。
+fn build_archive(
+    handle: &Handle,
+    client: &RegistryClient,
+    repository: &str,
+    manifest: &ManifestResponse,
+    manifest_bytes: &[u8],
+    writer: FanoutWriter,
+) -> Result<(u64, String, Option<String>)> {
+    let encoder = GzEncoder::new(BufWriter::with_capacity(1024 * 1024, writer), Compression::default());
+    let mut builder = Builder::new(encoder);
+
+    let mtime = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let mut platform = None;
+    let mut seen = HashSet::new();
+    let blobs: Vec<_> = std::iter::once(&manifest.config)
+        .chain(manifest.layers.iter())
+        .filter(|d| seen.insert(d.digest.clone()))
+        .collect();
+
+    for (idx, blob) in blobs.iter().enumerate() {
+        eprintln!(
+            "Streaming blob {}/{}: {} ({} bytes)",
+            idx + 1,
+            blobs.len(),
+            blob.digest,
+            blob.size
+        );
+
+        let response = handle.block_on(client.fetch_blob(repository, &blob.digest))?;
+        let body = response.bytes_stream().map(|chunk| chunk.map_err(std::io::Error::other));
+        let mut reader = HashingReader::new(SyncIoBridge::new_with_handle(
+            StreamReader::new(Box::pin(body)),
+            handle.clone(),
+        ));
+
+        let mut header = file_header(blob.size, mtime);
+        let path = format!("blobs/{}", blob.digest);
+        if blob.digest == manifest.config.digest {
+            // config 很小，读入内存以便记录平台
+            let mut config = Vec::new();
+            (&mut reader).take(blob.size).read_to_end(&mut config)?;
+            builder.append_data(&mut header, path, config.as_slice())?;
+            platform = sidecar::platform_of(&config);
+        } else {
+            builder.append_data(&mut header, path, (&mut reader).take(blob.size))?;
+        }
+
+        // 多余或缺少的数据都会使 digest 不一致
+        let mut rest = [0u8; 1];
+        if reader.read(&mut rest)? != 0 || reader.count != blob.size {
+            return Err(anyhow!("Blob size mismatch: {}", blob.digest));
+        }
+        let actual = format!("sha256:{:x}", reader.hasher.finalize_reset());
+        if actual != blob.digest {
+            return Err(anyhow!(
+                "Digest mismatch for {}: got {}",
+                blob.digest,
+                actual
+            ));
+        }
+    }
+
+    let mut header = file_header(manifest_bytes.len() as u64, mtime);
+    builder.append_data(&mut header, "manifest.json", manifest_bytes)?;
+
+    let writer = builder
+        .into_inner()?
+        .finish()?
+        .into_inner()
+        .map_err(|e| anyhow!("Failed to flush archive: {}", e.error()))?;
+
+    eprintln!("Archive streamed: {} bytes", writer.size);
+    Ok((writer.size, format!("sha256:{:x}", writer.hasher.finalize()), platform))
+}
+
+fn file_header(size: u64, mtime: u64) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_entry_type(tar::EntryType::Regular);
+    header
+}
+
+/// 将归档输出发送给所有目标，同时统计大小与 sha256
+struct FanoutWriter {
+    senders: Vec<mpsc::Sender<std::io::Result<Bytes>>>,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl FanoutWriter {
+    fn new(senders: Vec<mpsc::Sender<std::io::Result<Bytes>>>) -> Self {
+        Self {
+            senders,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+}
+
+impl Write for FanoutWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let chunk = Bytes::copy_from_slice(buf);
+        for sender in &self.senders {
+            sender
+                .blocking_send(Ok(chunk.clone()))
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "upload destination closed"))?;
+        }
+
+        self.hasher.update(buf);
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 读取时计算 sha256 的 Blob 读取器
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    count: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            count: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.count += n as u64;
+        Ok(n)
+    }
+}
@@ -1,16 +1,21 @@
+use crate::volume::ArchiveOutput;
 use anyhow::Result;
+use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use std::fs::File;
-use std::path::Path;
-use tar::Builder;
+use std::path::{Path, PathBuf};
+use tar::{Archive, Builder};
 
 /// 将目录打包成 tar.gz 文件
-pub fn create_tar_archive(input_dir: &Path, output_file: &Path) -> Result<()> {
+///
+/// 设置 `volume_size` 时按大小切分为 `<output>.001`、`.002` …… 并写入分卷索引。
+/// 返回生成的文件列表（分卷模式下索引文件在最后）。
+pub fn create_tar_archive(input_dir: &Path, output_file: &Path, volume_size: Option<u64>) -> Result<Vec<PathBuf>> {
     eprintln!("Creating tar archive: {}", output_file.display());
 
     // 创建输出文件
-    let output = File::create(output_file)?;
+    let output = ArchiveOutput::create(output_file, volume_size)?;
     let gz_encoder = GzEncoder::new(output, Compression::default());
     let mut tar_builder = Builder::new(gz_encoder);
 
@@ -18,10 +23,22 @@ pub fn create_tar_archive(input_dir: &Path, output_file: &Path) -> Result<()> {
     add_dir_to_tar(&mut tar_builder, input_dir, "")?;
 
     // 完成 tar 构建
-    tar_builder.finish()?;
+    let files = tar_builder.into_inner()?.finish()?.finish()?;
 
     eprintln!("Tar archive created successfully!");
 
+    Ok(files)
+}
+
+/// 将 tar.gz 文件解包到目录
+pub fn extract_tar_archive(input_file: &Path, output_dir: &Path) -> Result<()> {
+    eprintln!("Extracting tar archive: {}", input_file.display());
+
+    let input = File::open(input_file)?;
+    let mut archive = Archive::new(GzDecoder::new(input));
+    std::fs::create_dir_all(output_dir)?;
+    archive.unpack(output_dir)?;
+
     Ok(())
 }
 
@@ -61,8 +78,5 @@ fn add_dir_to_tar<W: std::io::Write>(
 
 /// 从镜像引用生成安全的文件名
 pub fn sanitize_filename(image_ref: &str) -> String {
-    image_ref
-        .replace(':', "_")
-        .replace('/', "_")
-        .replace('\\', "_")
+    image_ref.replace([':', '/', '\\'], "_")
 }
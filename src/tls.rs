@@ -0,0 +1,220 @@
+use crate::digest;
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, Error as TlsError, RootCertStore, SignatureScheme};
+
+/// 创建 TLS 客户端配置
+///
+/// - `ca_file`：PEM 格式的 CA 证书，用于自签名服务器；未设置时使用系统证书
+/// - `pinned_sha256`：服务器证书的 SHA-256 指纹，设置后只接受该证书（不校验证书链与主机名）
+///
+/// 同一个配置在多个连接间共享会话缓存，FTPS 数据连接可复用控制连接的 TLS 会话。
+pub fn client_config(ca_file: Option<&Path>, pinned_sha256: Option<&str>) -> Result<Arc<ClientConfig>> {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+
+    let config = match pinned_sha256 {
+        Some(fingerprint) => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+                fingerprint: normalize_fingerprint(fingerprint),
+                provider,
+            }))
+            .with_no_client_auth(),
+        None => builder
+            .with_root_certificates(root_store(ca_file)?)
+            .with_no_client_auth(),
+    };
+
+    Ok(Arc::new(config))
+}
+
+/// 将主机名转换为 TLS ServerName（支持 IP 地址）
+pub fn server_name(host: &str) -> Result<ServerName<'static>> {
+    ServerName::try_from(host.to_string()).map_err(|_| anyhow!("Invalid TLS server name: {}", host))
+}
+
+/// 明文或 TLS 连接（FTP 控制与数据连接、SMTP 连接），支持先明文再升级为 TLS
+pub enum MaybeTlsStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for MaybeTlsStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut TaskContext<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            MaybeTlsStream::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeTlsStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut TaskContext<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            MaybeTlsStream::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            MaybeTlsStream::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            MaybeTlsStream::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+fn root_store(ca_file: Option<&Path>) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+
+    match ca_file {
+        Some(path) => {
+            let pem = std::fs::read(path)
+                .map_err(|e| anyhow!("Failed to read CA certificate {}: {}", path.display(), e))?;
+            for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+                roots.add(cert?)?;
+            }
+            if roots.is_empty() {
+                return Err(anyhow!("No certificates found in {}", path.display()));
+            }
+        }
+        None => {
+            let native = rustls_native_certs::load_native_certs();
+            for error in &native.errors {
+                eprintln!("Warning: failed to load system certificate: {}", error);
+            }
+            roots.add_parsable_certificates(native.certs);
+        }
+    }
+
+    Ok(roots)
+}
+
+/// 指纹统一为小写十六进制，允许 `sha256:` 前缀与 `:` 分隔
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    let fingerprint = fingerprint.trim();
+    let fingerprint = fingerprint.strip_prefix("sha256:").unwrap_or(fingerprint);
+    fingerprint.replace(':', "").to_lowercase()
+}
+
+/// 证书固定：只比较服务器证书的指纹，握手签名仍正常校验
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    fingerprint: String,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, TlsError> {
+        let actual = digest::sha256_digest(end_entity);
+        if normalize_fingerprint(&actual) == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(TlsError::General(format!(
+                "Server certificate fingerprint mismatch: {}",
+                actual
+            )))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verify(pin: &str, cert: &[u8]) -> Result<ServerCertVerified, TlsError> {
+        let verifier = PinnedCertVerifier {
+            fingerprint: normalize_fingerprint(pin),
+            provider: Arc::new(crypto::ring::default_provider()),
+        };
+        verifier.verify_server_cert(
+            &CertificateDer::from(cert.to_vec()),
+            &[],
+            &server_name("ftp.example.com").unwrap(),
+            &[],
+            UnixTime::now(),
+        )
+    }
+
+    #[test]
+    fn pinned_fingerprint_matches_with_or_without_colons() {
+        let cert = b"server certificate";
+        let hex = digest::sha256_digest(cert).trim_start_matches("sha256:").to_string();
+        let with_colons = hex
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| std::str::from_utf8(pair).unwrap().to_uppercase())
+            .collect::<Vec<_>>()
+            .join(":");
+
+        for pin in [hex.clone(), format!("sha256:{}", hex), with_colons, format!(" {} ", hex.to_uppercase())] {
+            assert!(verify(&pin, cert).is_ok(), "pin {} should match", pin);
+        }
+    }
+
+    #[test]
+    fn pinned_fingerprint_mismatch_is_rejected() {
+        let pin = digest::sha256_digest(b"expected certificate");
+        let error = verify(&pin, b"other certificate").unwrap_err();
+        assert!(error.to_string().contains("Server certificate fingerprint mismatch"), "{}", error);
+    }
+
+    #[test]
+    fn ca_file_without_certificates_is_rejected() {
+        let path = std::env::temp_dir().join(format!("empty-ca-{}.pem", std::process::id()));
+        std::fs::write(&path, "not a certificate\n").unwrap();
+
+        let error = client_config(Some(&path), None).unwrap_err();
+        assert!(error.to_string().starts_with("No certificates found in"), "{}", error);
+        assert!(client_config(Some(Path::new("/nonexistent/ca.pem")), None).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
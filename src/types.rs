@@ -1,5 +1,24 @@
 use serde::{Deserialize, Serialize};
 
+/// Docker 镜像 Manifest 媒体类型
+pub const MEDIA_TYPE_DOCKER_MANIFEST: &str = "application/vnd.docker.distribution.manifest.v2+json";
+/// OCI 镜像 Manifest 媒体类型
+pub const MEDIA_TYPE_OCI_MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+
+/// OCI 空 config 描述符的媒体类型（内容为 `{}`）
+pub const MEDIA_TYPE_OCI_EMPTY: &str = "application/vnd.oci.empty.v1+json";
+/// Docker 镜像 config 媒体类型
+pub const MEDIA_TYPE_DOCKER_CONFIG: &str = "application/vnd.docker.container.image.v1+json";
+/// OCI 镜像 config 媒体类型
+pub const MEDIA_TYPE_OCI_CONFIG: &str = "application/vnd.oci.image.config.v1+json";
+/// layer 文件名注解
+pub const ANNOTATION_TITLE: &str = "org.opencontainers.image.title";
+
+/// 判断媒体类型是否为 Manifest List / OCI Index（多架构）
+pub fn is_index_media_type(media_type: &str) -> bool {
+    media_type.contains("manifest.list") || media_type.contains("index.v1")
+}
+
 /// Docker Registry V2 Manifest 响应
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ManifestResponse {
@@ -7,11 +26,37 @@ pub struct ManifestResponse {
     pub media_type: Option<String>,
     #[serde(rename = "schemaVersion")]
     pub schema_version: u32,
+    #[serde(rename = "artifactType", skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
     pub config: Descriptor,
+    #[serde(default)]
     pub layers: Vec<Descriptor>,
     pub annotations: Option<std::collections::HashMap<String, String>>,
 }
 
+impl ManifestResponse {
+    /// 是否为非镜像制品（Helm chart、WASM 模块、ORAS 文件等）
+    ///
+    /// 带有 `artifactType`，或 config 不是镜像 config 的 Manifest 都视为制品。
+    pub fn is_artifact(&self) -> bool {
+        if self.artifact_type.is_some() {
+            return true;
+        }
+
+        !matches!(
+            self.config.media_type.as_deref(),
+            None | Some(MEDIA_TYPE_DOCKER_CONFIG) | Some(MEDIA_TYPE_OCI_CONFIG)
+        )
+    }
+
+    /// 制品类型：优先 `artifactType`，否则使用 config 的媒体类型
+    pub fn artifact_type(&self) -> Option<&str> {
+        self.artifact_type
+            .as_deref()
+            .or(self.config.media_type.as_deref())
+    }
+}
+
 /// Docker Registry V2 Manifest List 响应（多架构）
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ManifestListResponse {
@@ -21,6 +66,17 @@ pub struct ManifestListResponse {
     pub manifests: Vec<PlatformDescriptor>,
 }
 
+/// OCI Referrers API 响应（以 OCI Index 形式返回）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReferrersResponse {
+    #[serde(rename = "mediaType")]
+    pub media_type: Option<String>,
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u32,
+    #[serde(default)]
+    pub manifests: Vec<Descriptor>,
+}
+
 /// Blob 描述符
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Descriptor {
@@ -29,16 +85,22 @@ pub struct Descriptor {
     pub size: u64,
     pub digest: String,
     pub platform: Option<Platform>,
+    #[serde(rename = "artifactType", skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<std::collections::HashMap<String, String>>,
 }
 
 /// 平台描述符（用于 Manifest List）
+///
+/// attestation Manifest 与制品等条目可能没有 `platform`。
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PlatformDescriptor {
     #[serde(rename = "mediaType")]
     pub media_type: Option<String>,
     pub size: u64,
     pub digest: String,
-    pub platform: Platform,
+    pub platform: Option<Platform>,
 }
 
 /// 平台信息
@@ -50,6 +112,43 @@ pub struct Platform {
     pub variant: Option<String>,
 }
 
+/// 镜像 config（只包含审查所需的字段）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImageConfig {
+    pub architecture: Option<String>,
+    pub os: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    pub created: Option<String>,
+    pub config: Option<ContainerConfig>,
+    #[serde(default)]
+    pub history: Vec<HistoryEntry>,
+}
+
+/// 镜像 config 中的容器运行配置
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerConfig {
+    pub user: Option<String>,
+    pub env: Option<Vec<String>>,
+    pub entrypoint: Option<Vec<String>>,
+    pub cmd: Option<Vec<String>>,
+    pub working_dir: Option<String>,
+    pub exposed_ports: Option<std::collections::HashMap<String, serde_json::Value>>,
+    pub labels: Option<std::collections::HashMap<String, String>>,
+}
+
+/// 镜像构建历史
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub created: Option<String>,
+    pub created_by: Option<String>,
+    #[serde(default)]
+    pub empty_layer: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
 /// Bearer Token 响应
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TokenResponse {
@@ -65,6 +164,7 @@ pub struct TokenResponse {
 pub struct AuthConfig {
     pub realm: String,
     pub service: Option<String>,
+    #[allow(dead_code)] // 解析后保留，scope 由调用方按操作传入
     pub scope: Option<String>,
 }
 
@@ -75,12 +175,3 @@ pub struct ImageReference {
     pub repository: String,
     pub reference: String,
 }
-
-/// 下载进度信息
-#[derive(Debug, Clone)]
-pub struct DownloadProgress {
-    pub total_bytes: u64,
-    pub downloaded_bytes: u64,
-    pub current_layer: usize,
-    pub total_layers: usize,
-}
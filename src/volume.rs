@@ -0,0 +1,352 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// 分卷索引文件后缀：`<archive>.volumes.json`
+pub const INDEX_SUFFIX: &str = ".volumes.json";
+
+/// 分卷索引：按顺序拼接各分卷即得到完整归档
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeIndex {
+    /// 完整归档的文件名
+    pub archive: String,
+    /// 完整归档大小
+    pub size: u64,
+    /// 完整归档的 sha256 digest
+    pub digest: String,
+    /// 分卷大小（最后一卷可能更小）
+    pub volume_size: u64,
+    pub volumes: Vec<Volume>,
+}
+
+/// 单个分卷
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Volume {
+    /// 分卷文件名，与索引文件位于同一目录
+    pub name: String,
+    pub size: u64,
+    pub digest: String,
+}
+
+/// 解析分卷大小，支持 `K`/`M`/`G`/`T` 后缀（1024 进制）
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => value.split_at(pos),
+        None => (value, ""),
+    };
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(anyhow!("Invalid volume size: {}", value)),
+    };
+
+    let size = number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| anyhow!("Invalid volume size: {}", value))?;
+    if size == 0 {
+        return Err(anyhow!("Volume size must be greater than 0"));
+    }
+
+    Ok(size)
+}
+
+/// 分卷索引文件路径
+pub fn index_path(archive: &Path) -> PathBuf {
+    let mut name = archive.as_os_str().to_os_string();
+    name.push(INDEX_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// 是否为分卷索引文件
+pub fn is_index(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(INDEX_SUFFIX)
+}
+
+/// 归档输出：单个文件，或按大小切分的分卷
+pub enum ArchiveOutput {
+    File(PathBuf, BufWriter<File>),
+    Volumes(Box<VolumeWriter>),
+}
+
+impl ArchiveOutput {
+    /// 创建输出；设置 `volume_size` 时写入 `<archive>.001`、`.002` ……
+    pub fn create(path: &Path, volume_size: Option<u64>) -> Result<Self> {
+        match volume_size {
+            Some(volume_size) => Ok(ArchiveOutput::Volumes(Box::new(VolumeWriter::new(path, volume_size)?))),
+            None => Ok(ArchiveOutput::File(
+                path.to_path_buf(),
+                BufWriter::with_capacity(1024 * 1024, File::create(path)?),
+            )),
+        }
+    }
+
+    /// 完成写入，返回生成的文件（分卷模式下索引文件在最后）
+    pub fn finish(self) -> Result<Vec<PathBuf>> {
+        match self {
+            ArchiveOutput::File(path, mut writer) => {
+                writer.flush()?;
+                Ok(vec![path])
+            }
+            ArchiveOutput::Volumes(writer) => writer.finish(),
+        }
+    }
+}
+
+impl Write for ArchiveOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            ArchiveOutput::File(_, writer) => writer.write(buf),
+            ArchiveOutput::Volumes(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            ArchiveOutput::File(_, writer) => writer.flush(),
+            ArchiveOutput::Volumes(writer) => writer.flush(),
+        }
+    }
+}
+
+/// 写满 `volume_size` 后切换到下一个分卷，同时计算每卷与整体的 sha256
+pub struct VolumeWriter {
+    archive: PathBuf,
+    volume_size: u64,
+    current: Option<(BufWriter<File>, Sha256, u64)>,
+    volumes: Vec<Volume>,
+    files: Vec<PathBuf>,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl VolumeWriter {
+    pub fn new(archive: &Path, volume_size: u64) -> Result<Self> {
+        // 删除上次运行留下的分卷，避免与新分卷混在一起
+        for n in 1.. {
+            let stale = volume_path(archive, n);
+            if !stale.exists() {
+                break;
+            }
+            std::fs::remove_file(&stale)?;
+        }
+
+        Ok(Self {
+            archive: archive.to_path_buf(),
+            volume_size,
+            current: None,
+            volumes: Vec::new(),
+            files: Vec::new(),
+            hasher: Sha256::new(),
+            size: 0,
+        })
+    }
+
+    /// 关闭当前分卷并记录其大小与 digest
+    fn close_volume(&mut self) -> std::io::Result<()> {
+        if let Some((mut writer, hasher, size)) = self.current.take() {
+            writer.flush()?;
+            let path = self.files.last().cloned().unwrap_or_default();
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            eprintln!("Volume written: {} ({} bytes)", name, size);
+            self.volumes.push(Volume {
+                name,
+                size,
+                digest: format!("sha256:{:x}", hasher.finalize()),
+            });
+        }
+        Ok(())
+    }
+
+    /// 关闭最后一个分卷并写入索引文件
+    pub fn finish(mut self) -> Result<Vec<PathBuf>> {
+        self.close_volume()?;
+
+        let index = VolumeIndex {
+            archive: self
+                .archive
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            size: self.size,
+            digest: format!("sha256:{:x}", self.hasher.finalize()),
+            volume_size: self.volume_size,
+            volumes: self.volumes,
+        };
+        let index_path = index_path(&self.archive);
+        std::fs::write(&index_path, serde_json::to_string_pretty(&index)?)?;
+        eprintln!("Volume index written: {} ({} volumes)", index_path.display(), index.volumes.len());
+
+        self.files.push(index_path);
+        Ok(self.files)
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.current.as_ref().is_some_and(|(_, _, size)| *size >= self.volume_size) {
+            self.close_volume()?;
+        }
+        if self.current.is_none() {
+            let path = volume_path(&self.archive, self.files.len() + 1);
+            let file = File::create(&path)?;
+            self.files.push(path);
+            self.current = Some((BufWriter::with_capacity(1024 * 1024, file), Sha256::new(), 0));
+        }
+
+        let Some((writer, hasher, size)) = self.current.as_mut() else {
+            unreachable!()
+        };
+        let len = buf.len().min(usize::try_from(self.volume_size - *size).unwrap_or(usize::MAX));
+        let n = writer.write(&buf[..len])?;
+        hasher.update(&buf[..n]);
+        *size += n as u64;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.current.as_mut() {
+            Some((writer, _, _)) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 第 `n` 个分卷的路径：`<archive>.001`
+fn volume_path(archive: &Path, n: usize) -> PathBuf {
+    let mut name = archive.as_os_str().to_os_string();
+    name.push(format!(".{:03}", n));
+    PathBuf::from(name)
+}
+
+/// 读取索引并校验所有分卷的大小与 digest
+pub fn verify(index_file: &Path) -> Result<VolumeIndex> {
+    let index = read_index(index_file)?;
+    let dir = index_file.parent().unwrap_or(Path::new("."));
+
+    for volume in &index.volumes {
+        copy_volume(&dir.join(&volume.name), volume, &mut std::io::sink())?;
+    }
+
+    Ok(index)
+}
+
+/// 校验并按顺序拼接分卷，返回完整归档大小
+///
+/// 每个分卷边复制边校验，最后校验完整归档的 digest；失败时删除输出文件。
+pub fn join(index_file: &Path, output_file: &Path) -> Result<u64> {
+    let index = read_index(index_file)?;
+    let dir = index_file.parent().unwrap_or(Path::new("."));
+
+    let result = (|| {
+        let mut output = HashingWriter {
+            inner: BufWriter::with_capacity(1024 * 1024, File::create(output_file)?),
+            hasher: Sha256::new(),
+        };
+        for volume in &index.volumes {
+            copy_volume(&dir.join(&volume.name), volume, &mut output)?;
+        }
+        output.inner.flush()?;
+
+        let actual = format!("sha256:{:x}", output.hasher.finalize());
+        if actual != index.digest {
+            return Err(anyhow!(
+                "Digest mismatch for {}: expected {}, got {}",
+                index.archive,
+                index.digest,
+                actual
+            ));
+        }
+        Ok(index.size)
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(output_file);
+    }
+    result
+}
+
+/// 读取分卷索引，并检查分卷大小之和与归档大小一致
+pub fn read_index(index_file: &Path) -> Result<VolumeIndex> {
+    let content = std::fs::read(index_file)
+        .with_context(|| format!("Failed to read volume index {}", index_file.display()))?;
+    let index: VolumeIndex = serde_json::from_slice(&content)
+        .with_context(|| format!("Invalid volume index {}", index_file.display()))?;
+
+    let total: u64 = index.volumes.iter().map(|v| v.size).sum();
+    if total != index.size {
+        return Err(anyhow!(
+            "Volume sizes add up to {} bytes, index says {}",
+            total,
+            index.size
+        ));
+    }
+
+    Ok(index)
+}
+
+/// 将分卷复制到 `output`，同时校验大小与 digest
+fn copy_volume(path: &Path, volume: &Volume, output: &mut impl Write) -> Result<()> {
+    let mut file = File::open(path).with_context(|| format!("Missing volume {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut size = 0u64;
+
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        output.write_all(&buffer[..n])?;
+        size += n as u64;
+    }
+
+    let actual = format!("sha256:{:x}", hasher.finalize());
+    if size != volume.size || actual != volume.digest {
+        return Err(anyhow!(
+            "Volume {} is corrupt: expected {} bytes {}, got {} bytes {}",
+            volume.name,
+            volume.size,
+            volume.digest,
+            size,
+            actual
+        ));
+    }
+    eprintln!("Volume verified: {}", volume.name);
+
+    Ok(())
+}
+
+/// 写入时计算 sha256
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,340 @@
+use crate::destination::{self, ByteStream, Destination, RemoteFile};
+use crate::retention::RetentionPolicy;
+use crate::xml::{xml_elements, xml_text};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::{Body, Client, Method, RequestBuilder, Response, StatusCode, Url};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio_util::io::ReaderStream;
+
+/// PROPFIND 请求体：只需要类型、大小与修改时间
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:propfind xmlns:D="DAV:"><D:prop><D:resourcetype/><D:getcontentlength/><D:getlastmodified/></D:prop></D:propfind>"#;
+
+/// WebDAV 目标配置
+#[derive(Debug, Clone)]
+pub struct WebDavConfig {
+    /// 集合（目录）地址，以 `/` 结尾
+    pub url: Url,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// 上传后清理集合中的旧归档
+    pub retention: Option<RetentionPolicy>,
+}
+
+impl WebDavConfig {
+    /// `webdav://host/path/`（HTTP）或 `webdavs://host/path/`（HTTPS），认证信息从 URL 中取出
+    ///
+    /// 查询参数：`keep`、`days`。
+    pub fn from_url(url: &Url) -> Result<Self> {
+        let (username, password) = destination::credentials(url);
+        let scheme = if url.scheme() == "webdavs" { "https" } else { "http" };
+        let host = url.host_str().ok_or_else(|| anyhow!("Missing host in WebDAV URL"))?;
+        let port = url.port().map(|p| format!(":{}", p)).unwrap_or_default();
+
+        let mut path = url.path().to_string();
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        let base = format!("{}://{}{}{}", scheme, host, port, path);
+
+        Ok(Self {
+            url: Url::parse(&base).map_err(|e| anyhow!("Invalid WebDAV URL {}: {}", base, e))?,
+            username,
+            password,
+            retention: destination::retention(url)?,
+        })
+    }
+
+    /// 文件地址（路径各段分别编码）
+    fn file_url(&self, name: &str) -> Result<Url> {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Invalid WebDAV URL: {}", self.url))?
+            .pop_if_empty()
+            .extend(name.split('/'));
+        Ok(url)
+    }
+
+    fn request(&self, method: Method, url: Url) -> Result<RequestBuilder> {
+        let client = Client::builder().timeout(Duration::from_secs(6 * 3600)).build()?;
+        let mut request = client.request(method, url);
+        if let Some(username) = &self.username {
+            request = request.basic_auth(username, self.password.as_deref());
+        }
+        Ok(request)
+    }
+
+    /// 逐级创建基础集合与 `name` 的父集合（MKCOL），已存在时服务器返回 405
+    ///
+    /// 基础集合之上的层级可能不可写（如 Nextcloud 的 `/remote.php/dav/`），忽略这些层级的错误。
+    async fn mkcol_parents(&self, name: &str) -> Result<()> {
+        let base: Vec<&str> = self.url.path().split('/').filter(|s| !s.is_empty()).collect();
+        let dirs: Vec<&str> = name.split('/').collect();
+        let dirs = &dirs[..dirs.len() - 1];
+        let collections: Vec<String> = base
+            .iter()
+            .map(|s| s.to_string())
+            .chain(dirs.iter().map(|s| s.to_string()))
+            .collect();
+
+        for depth in 1..=collections.len() {
+            let mut url = self.url.clone();
+            url.path_segments_mut()
+                .map_err(|_| anyhow!("Invalid WebDAV URL: {}", self.url))?
+                .clear()
+                .extend(&collections[..depth])
+                .push("");
+
+            let response = self.request(Method::from_bytes(b"MKCOL")?, url)?.send().await?;
+            match response.status() {
+                status if status.is_success() => {}
+                StatusCode::METHOD_NOT_ALLOWED => {}
+                _ if depth < base.len() => {}
+                status => return Err(anyhow!("WebDAV MKCOL failed: {}", status)),
+            }
+        }
+        Ok(())
+    }
+
+    /// PUT 数据到 `name`
+    async fn put_body(&self, name: &str, body: Body, size: Option<u64>) -> Result<Url> {
+        self.mkcol_parents(name).await?;
+
+        let url = self.file_url(name)?;
+        eprintln!("Uploading to {}...", url);
+        let mut request = self.request(Method::PUT, url.clone())?.body(body);
+        if let Some(size) = size {
+            request = request.header(CONTENT_LENGTH, size);
+        }
+        check(request.send().await?, "PUT").await?;
+        Ok(url)
+    }
+
+    /// PROPFIND，返回 multistatus 响应体
+    async fn propfind(&self, url: Url, depth: &str) -> Result<Option<String>> {
+        let response = self
+            .request(Method::from_bytes(b"PROPFIND")?, url)?
+            .header("Depth", depth)
+            .header("Content-Type", "application/xml")
+            .body(PROPFIND_BODY)
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(check(response, "PROPFIND").await?))
+    }
+
+    /// MOVE，目标已存在时覆盖
+    async fn move_file(&self, from: &str, to: &str) -> Result<()> {
+        self.mkcol_parents(to).await?;
+        let response = self
+            .request(Method::from_bytes(b"MOVE")?, self.file_url(from)?)?
+            .header("Destination", self.file_url(to)?.as_str())
+            .header("Overwrite", "T")
+            .send()
+            .await?;
+        check(response, "MOVE").await?;
+        Ok(())
+    }
+
+    /// 上传后通过 PROPFIND 校验大小
+    async fn verify_size(&self, name: &str, expected: u64) -> Result<()> {
+        let size = self.stat_file(name).await?.and_then(|f| f.size);
+        if size != Some(expected) {
+            return Err(anyhow!(
+                "Remote size mismatch for {}: expected {}, got {}",
+                name,
+                expected,
+                size.map_or("none".to_string(), |s| s.to_string())
+            ));
+        }
+        eprintln!("Remote size verified: {} bytes", expected);
+        Ok(())
+    }
+
+    /// 上传本地文件：写入 `.<name>.part`，校验大小后 MOVE 为正式文件名
+    async fn put_file(&self, local_path: &Path, name: &str) -> Result<String> {
+        let size = tokio::fs::metadata(local_path).await?.len();
+        let file = tokio::fs::File::open(local_path).await?;
+        let temp_name = temp_name(name);
+
+        let result = async {
+            self.put_body(&temp_name, Body::wrap_stream(ReaderStream::new(file)), Some(size))
+                .await?;
+            self.verify_size(&temp_name, size).await?;
+            self.move_file(&temp_name, name).await
+        }
+        .await;
+        if result.is_err() {
+            let _ = self.delete_file(&temp_name).await;
+        }
+        result?;
+
+        Ok(self.file_url(name)?.to_string())
+    }
+
+    /// 流式上传：写入 `.<name>.part`，确认大小后 MOVE 为正式文件名
+    async fn put_data(
+        &self,
+        name: &str,
+        data: ByteStream,
+        summary: oneshot::Receiver<(u64, String)>,
+    ) -> Result<String> {
+        let temp_name = temp_name(name);
+
+        let result = async {
+            self.put_body(&temp_name, Body::wrap_stream(data), None).await?;
+            let (size, _) = summary.await.map_err(|_| anyhow!("Archive stream aborted"))?;
+            self.verify_size(&temp_name, size).await?;
+            self.move_file(&temp_name, name).await
+        }
+        .await;
+        if result.is_err() {
+            let _ = self.delete_file(&temp_name).await;
+        }
+        result?;
+
+        Ok(self.file_url(name)?.to_string())
+    }
+
+    async fn stat_file(&self, name: &str) -> Result<Option<RemoteFile>> {
+        let Some(body) = self.propfind(self.file_url(name)?, "0").await? else {
+            return Ok(None);
+        };
+        Ok(xml_elements(&body, "response")
+            .first()
+            .map(|response| parse_response(name.to_string(), response)))
+    }
+
+    async fn list_files(&self) -> Result<Vec<RemoteFile>> {
+        let Some(body) = self.propfind(self.url.clone(), "1").await? else {
+            return Ok(Vec::new());
+        };
+
+        let mut files = Vec::new();
+        for response in xml_elements(&body, "response") {
+            // 集合本身与子集合不列出
+            if !xml_elements(response, "collection").is_empty() {
+                continue;
+            }
+            let Some(href) = xml_text(response, "href") else {
+                continue;
+            };
+            let href = percent_encoding::percent_decode_str(&href).decode_utf8_lossy().to_string();
+            let Some(name) = href.trim_end_matches('/').rsplit('/').next().filter(|n| !n.is_empty()) else {
+                continue;
+            };
+            files.push(parse_response(name.to_string(), response));
+        }
+
+        Ok(files)
+    }
+
+    async fn delete_file(&self, name: &str) -> Result<()> {
+        let response = self.request(Method::DELETE, self.file_url(name)?)?.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(response, "DELETE").await?;
+        Ok(())
+    }
+}
+
+impl Destination for WebDavConfig {
+    fn location(&self) -> String {
+        self.url.to_string()
+    }
+
+    fn output_key(&self) -> &'static str {
+        "webdav_url"
+    }
+
+    fn retention(&self) -> Option<&RetentionPolicy> {
+        self.retention.as_ref()
+    }
+
+    fn put<'a>(&'a self, local_path: &'a Path, name: &'a str) -> BoxFuture<'a, Result<String>> {
+        self.put_file(local_path, name).boxed()
+    }
+
+    fn put_stream<'a>(
+        &'a self,
+        name: &'a str,
+        data: ByteStream,
+        summary: oneshot::Receiver<(u64, String)>,
+    ) -> BoxFuture<'a, Result<String>> {
+        self.put_data(name, data, summary).boxed()
+    }
+
+    fn get<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<u8>>> {
+        async move {
+            let response = self.request(Method::GET, self.file_url(name)?)?.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(anyhow!("WebDAV GET failed: {}", status));
+            }
+            Ok(response.bytes().await?.to_vec())
+        }
+        .boxed()
+    }
+
+    fn download<'a>(&'a self, name: &'a str, local_path: &'a Path) -> BoxFuture<'a, Result<u64>> {
+        async move {
+            let response = self.request(Method::GET, self.file_url(name)?)?.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(anyhow!("WebDAV GET failed: {}", status));
+            }
+            destination::write_body(response, local_path).await
+        }
+        .boxed()
+    }
+
+    fn stat<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Option<RemoteFile>>> {
+        self.stat_file(name).boxed()
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<RemoteFile>>> {
+        self.list_files().boxed()
+    }
+
+    fn delete<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.delete_file(name).boxed()
+    }
+}
+
+/// 与 `name` 同目录的临时文件名 `.<name>.part`
+fn temp_name(name: &str) -> String {
+    match name.rsplit_once('/') {
+        Some((dir, file_name)) => format!("{}/.{}.part", dir, file_name),
+        None => format!(".{}.part", name),
+    }
+}
+
+/// 解析 multistatus 中的一个 `<response>`
+fn parse_response(name: String, response: &str) -> RemoteFile {
+    RemoteFile {
+        name,
+        size: xml_text(response, "getcontentlength").and_then(|s| s.parse().ok()),
+        modified: xml_text(response, "getlastmodified")
+            .and_then(|t| DateTime::parse_from_rfc2822(&t).ok())
+            .map(|t| t.with_timezone(&Utc)),
+    }
+}
+
+/// 检查响应状态，返回响应体
+async fn check(response: Response, operation: &str) -> Result<String> {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(anyhow!("WebDAV {} failed: {} - {}", operation, status, body));
+    }
+    Ok(body)
+}
@@ -0,0 +1,66 @@
+/// 取出所有 `<tag>...</tag>` 元素的内容
+///
+/// 只做 S3 / WebDAV 响应需要的最简单解析：忽略命名空间前缀（`<D:href>` 与 `<href>` 相同）与属性，
+/// 不支持同名元素嵌套。自闭合元素（`<tag/>`）的内容为空。
+pub fn xml_elements<'a>(body: &'a str, tag: &str) -> Vec<&'a str> {
+    let mut elements = Vec::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find('<') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('>') else {
+            break;
+        };
+        let open = &after[..end];
+        rest = &after[end + 1..];
+
+        // 标签名：到空白或 `/` 为止
+        let name = open
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        if name.is_empty() || local_name(name) != tag {
+            continue;
+        }
+        if open.ends_with('/') {
+            elements.push("");
+            continue;
+        }
+
+        let close = format!("</{}>", name);
+        match rest.find(&close) {
+            Some(end) => {
+                elements.push(&rest[..end]);
+                rest = &rest[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+
+    elements
+}
+
+/// 第一个 `<tag>` 元素的文本（已反转义）
+pub fn xml_text(body: &str, tag: &str) -> Option<String> {
+    xml_elements(body, tag).first().map(|text| xml_unescape(text.trim()))
+}
+
+/// 去掉命名空间前缀
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+pub fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn xml_unescape(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}